@@ -0,0 +1,79 @@
+//! Files kept with entries, like screenshots and logs, added with
+//! `code_diary add --attach` and read back with `code_diary attachment get`.
+//!
+//! The contents are stored once per distinct file in the `Blobs` table, keyed
+//! by their BLAKE2 hash, so attaching the same log to several entries does
+//! not store it twice. When the diary is encrypted, the contents and names are
+//! sealed like entry contents and the hash is keyed, see [`crate::crypto`].
+
+use std::path::Path;
+
+use crate::crypto;
+use crate::fail;
+
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
+/// A file attached to an entry
+pub struct Attachment {
+    /// The file name it was attached under, unique within the entry
+    pub name: String,
+    /// The hex BLAKE2 hash of the contents, keyed in an encrypted diary, which
+    /// identifies the blob
+    pub hash: String,
+    /// The size of the contents in bytes
+    pub size: u64,
+}
+
+impl Attachment {
+    /// Reads the file at `path`, unless it is larger than `max_mb` megabytes,
+    /// and returns it with its contents, which still have to be stored with
+    /// [`store_blob`](crate::backend::store_blob)
+    /// # Panics
+    /// Will 'gracefully' exit if the file cannot be read or is too large
+    pub fn read(path: &str, max_mb: u64) -> (Self, Vec<u8>) {
+        let name = match Path::new(path).file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => fail!("Cannot attach {path}: it is not a file"),
+        };
+        let size = match std::fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            Ok(_) => fail!("Cannot attach {path}: it is not a file"),
+            Err(e) => fail!("Cannot attach {path}: {e}"),
+        };
+        if size > max_mb.saturating_mul(1024 * 1024) {
+            let size = show_size(size);
+            fail!("Cannot attach {path}: it is {size}, more than the {max_mb} MB allowed by max_attachment_mb");
+        }
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(e) => fail!("Cannot attach {path}: {e}"),
+        };
+
+        let hash = crypto::hash_blob(&data);
+        (Self { name, hash, size }, data)
+    }
+}
+
+/// Checks that no two of `attachments` share a name, which is how
+/// `attachment get` tells them apart
+/// # Panics
+/// Will 'gracefully' exit if a name is repeated
+pub fn check_names(attachments: &[(Attachment, Vec<u8>)]) {
+    for (index, (attachment, _)) in attachments.iter().enumerate() {
+        if attachments[..index]
+            .iter()
+            .any(|(other, _)| other.name == attachment.name)
+        {
+            let name = &attachment.name;
+            fail!("Two attachments are called {name}; rename one of them");
+        }
+    }
+}
+
+/// Formats `size` in bytes for people, e.g. `1.2 MB`
+pub fn show_size(size: u64) -> String {
+    match size {
+        0..1024 => format!("{size} B"),
+        1024..1_048_576 => format!("{:.1} KB", size as f64 / 1024.0),
+        _ => format!("{:.1} MB", size as f64 / 1_048_576.0),
+    }
+}
@@ -2,27 +2,21 @@
 //! mostly includes functions and macros related to backend database operations
 //! and anything that persists in memory
 
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use chacha20poly1305::XChaCha20Poly1305;
+use chrono::{Datelike, NaiveDate};
+
+use crate::attachments::Attachment;
+use crate::crypto;
 use crate::diaryentry::DiaryEntry;
+use crate::doctor::Problem;
+use crate::links;
+use crate::refs::FileRef;
 use crate::timestamps::TimeStamp;
 
-macro_rules! iter {
-    ($name:expr) => {{
-        let mut list: Vec<String> = Vec::new();
-        let db = open_db();
-        let query = format!("SELECT {} FROM Entries;", $name);
-        db.iterate(query, |pairs| {
-            let val = pairs[0].1;
-            if val.is_none() {
-                fail!("Database Error: Could not read value from DB!");
-            }
-            list.push(val.unwrap().to_string());
-            true
-        })
-        .unwrap();
-        list
-    }};
-}
-
 #[macro_export]
 /// A convenience macro for exiting with a nonzero status code with a message
 /// Example:
@@ -45,44 +39,507 @@ macro_rules! fail {
 }
 
 /// Gets the latest id. What this meaans is that if there are four diary
-/// entries being stored, for example, the function will return 4.
+/// entries being stored, for example, the function will return 4. Ids of
+/// quarantined entries count too, so they are never handed out again.
 pub fn get_latest_id() -> u64 {
-    let mut id_list: Vec<u64> = iter!("Id")
-        .iter()
-        .map(|x| x.parse::<u64>().unwrap())
-        .collect::<Vec<u64>>();
-    id_list.sort_by(|b, a| a.cmp(b));
-    let id = match id_list.len() {
-        0 => 0,
-        _ => id_list[0],
+    let db = open_db();
+    let mut statement = prepare(
+        &db,
+        &format!("SELECT coalesce(max(Id), 0) FROM ({KEPT_IDS}) WHERE typeof(Id) = 'integer';"),
+    );
+    match statement.next() {
+        Ok(sqlite::State::Row) => statement.read::<i64, _>(0).unwrap() as u64,
+        _ => fail!("Database Error: Could not read value from DB!"),
+    }
+}
+
+/// The notebook entries go to when none is selected
+pub const DEFAULT_NOTEBOOK: &str = "default";
+
+static NOTEBOOK: OnceLock<String> = OnceLock::new();
+static DB_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Schema changes applied in order on top of the original `Entries` table. The
+/// number of migrations applied so far is kept in `PRAGMA user_version`, so new
+/// changes must only ever be appended.
+const MIGRATIONS: &[&str] = &[
+    "ALTER TABLE Entries ADD COLUMN Notebook TEXT NOT NULL DEFAULT 'default';
+     CREATE TABLE IF NOT EXISTS Notebooks (Name TEXT PRIMARY KEY);
+     INSERT OR IGNORE INTO Notebooks VALUES ('default');",
+    "CREATE TABLE IF NOT EXISTS Tags (EntryId INTEGER NOT NULL, Tag TEXT NOT NULL, UNIQUE (EntryId, Tag));",
+    "ALTER TABLE Entries ADD COLUMN Repo TEXT;",
+    "CREATE TABLE IF NOT EXISTS Commits (Hash TEXT PRIMARY KEY, EntryId INTEGER NOT NULL);",
+    "CREATE TABLE IF NOT EXISTS Refs (EntryId INTEGER NOT NULL, Path TEXT NOT NULL, FirstLine INTEGER, LastLine INTEGER, Root TEXT, CommitHash TEXT);",
+    "CREATE TABLE IF NOT EXISTS EntryLinks (SourceId INTEGER NOT NULL, TargetId INTEGER NOT NULL, UNIQUE (SourceId, TargetId));",
+    "CREATE TABLE IF NOT EXISTS Blobs (Hash TEXT PRIMARY KEY, Data BLOB NOT NULL);
+     CREATE TABLE IF NOT EXISTS Attachments (EntryId INTEGER NOT NULL, Name TEXT NOT NULL, Hash TEXT NOT NULL, Size INTEGER NOT NULL, UNIQUE (EntryId, Name));",
+    "CREATE TABLE IF NOT EXISTS Sessions (EntryId INTEGER PRIMARY KEY, End TEXT NOT NULL);",
+    "CREATE TABLE IF NOT EXISTS Fields (EntryId INTEGER NOT NULL, Name TEXT NOT NULL, Value INTEGER NOT NULL, UNIQUE (EntryId, Name));",
+    "ALTER TABLE Entries ADD COLUMN Pinned INTEGER NOT NULL DEFAULT 0;
+     ALTER TABLE Entries ADD COLUMN Starred INTEGER NOT NULL DEFAULT 0;",
+    "CREATE TABLE IF NOT EXISTS Quarantine (Reason TEXT NOT NULL, Id, TimeStamp, Title, Content, Notebook, Repo);",
+];
+
+/// The tables holding rows that belong to entries, and the columns holding
+/// the ids of those entries. Blobs belong to attachments instead.
+const SIDE_TABLES: &[(&str, &[&str])] = &[
+    ("Tags", &["EntryId"]),
+    ("Commits", &["EntryId"]),
+    ("Refs", &["EntryId"]),
+    ("EntryLinks", &["SourceId", "TargetId"]),
+    ("Attachments", &["EntryId"]),
+    ("Blobs", &[]),
+    ("Sessions", &["EntryId"]),
+    ("Fields", &["EntryId"]),
+];
+
+/// The ids that rows in [`SIDE_TABLES`] may belong to. Entries moved to the
+/// `Quarantine` table keep their rows, so they can still be put back whole.
+const KEPT_IDS: &str = "SELECT Id FROM Entries WHERE Id IS NOT NULL
+                        UNION SELECT Id FROM Quarantine WHERE Id IS NOT NULL";
+
+/// Returns the condition under which a row of a table in [`SIDE_TABLES`],
+/// whose `columns` hold entry ids, belongs to no entry
+fn orphaned(columns: &[&str]) -> String {
+    match columns.is_empty() {
+        true => String::from("Hash NOT IN (SELECT Hash FROM Attachments)"),
+        false => columns
+            .iter()
+            .map(|column| format!("{column} NOT IN ({KEPT_IDS})"))
+            .collect::<Vec<_>>()
+            .join(" OR "),
+    }
+}
+
+/// Removes the contents of files no longer attached to any entry
+const DELETE_UNUSED_BLOBS: &str =
+    "DELETE FROM Blobs WHERE Hash NOT IN (SELECT Hash FROM Attachments);";
+
+/// Sets where the database lives, normally from
+/// [`Config::db_path`](crate::config::Config::db_path). Must be called before
+/// the database is first opened; otherwise `~/code_diary.db` is used.
+pub fn set_db_path(path: PathBuf) {
+    if DB_PATH.set(path).is_err() {
+        fail!("The database path has already been set!");
+    }
+}
+
+/// Returns the path of the database file
+pub fn db_path() -> PathBuf {
+    DB_PATH
+        .get_or_init(|| PathBuf::from(std::env::var("HOME").unwrap()).join("code_diary.db"))
+        .clone()
+}
+
+/// Writes a consistent copy of the database to `path`, which must not exist
+/// yet, even while another process is writing to it
+/// # Panics
+/// Will 'gracefully' exit if the copy cannot be written
+pub fn snapshot(path: &Path) {
+    let db = open_db();
+    let mut statement = prepare(&db, "VACUUM INTO ?;");
+    statement
+        .bind((1, path.to_string_lossy().as_ref()))
+        .unwrap();
+    if let Err(e) = statement.next() {
+        let path = path.display();
+        fail!("Could not back up the diary to {path}: {e}");
+    }
+}
+
+/// Checks that the file at `path` is an intact diary database, returning
+/// what is wrong with it otherwise
+pub fn check_database(path: &Path) -> Result<(), String> {
+    let flags = sqlite::OpenFlags::new().set_read_only();
+    let db = sqlite::Connection::open_with_flags(path, flags).map_err(|e| e.to_string())?;
+    let problems = integrity_check(&db)?;
+    if !problems.is_empty() {
+        return Err(problems.join("; "));
+    }
+    let mut tables = 0;
+    db.iterate(
+        "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = 'Entries';",
+        |pairs| {
+            tables = pairs[0].1.and_then(|count| count.parse().ok()).unwrap_or(0);
+            true
+        },
+    )
+    .map_err(|e| e.to_string())?;
+    match tables {
+        0 => Err(String::from("it has no entries table")),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the damage `PRAGMA integrity_check` finds in `db`
+fn integrity_check(db: &sqlite::Connection) -> Result<Vec<String>, String> {
+    let mut problems = Vec::new();
+    db.iterate("PRAGMA integrity_check;", |pairs| {
+        problems.extend(
+            pairs[0]
+                .1
+                .filter(|&result| result != "ok")
+                .map(String::from),
+        );
+        true
+    })
+    .map_err(|e| e.to_string())?;
+    Ok(problems)
+}
+
+/// Looks for damage to the file, entries that cannot be read, entries
+/// sharing an id and rows in other tables that belong to no entry
+/// # Panics
+/// Will 'gracefully' exit if the database cannot be read at all
+pub fn diagnose() -> Vec<Problem> {
+    let db = open_db();
+    let mut problems: Vec<Problem> = match integrity_check(&db) {
+        Ok(damage) => damage.into_iter().map(Problem::Corruption).collect(),
+        Err(e) => vec![Problem::Corruption(e)],
     };
-    id
+
+    let mut statement = prepare(
+        &db,
+        "SELECT rowid, CASE typeof(Id) WHEN 'integer' THEN Id END, CAST(TimeStamp AS TEXT),
+                Title IS NULL, Content IS NULL, Notebook IS NULL
+         FROM Entries ORDER BY rowid;",
+    );
+    for row in statement.iter() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        };
+        let rowid = row.read::<i64, _>(0);
+        let id = row.read::<Option<i64>, _>(1);
+        let timestamp = row.read::<Option<&str>, _>(2);
+        if id.is_none() {
+            problems.push(Problem::BadId { rowid });
+        }
+        if timestamp.and_then(TimeStamp::from_string).is_none() {
+            problems.push(Problem::BadTimestamp {
+                rowid,
+                id,
+                timestamp: timestamp.map(String::from),
+            });
+        }
+        for (index, column) in [(3, "Title"), (4, "Content"), (5, "Notebook")] {
+            if row.read::<i64, _>(index) != 0 {
+                problems.push(Problem::Null { rowid, id, column });
+            }
+        }
+    }
+
+    let mut statement = prepare(
+        &db,
+        "SELECT Id, group_concat(rowid) FROM Entries WHERE typeof(Id) = 'integer'
+         GROUP BY Id HAVING count(*) > 1 ORDER BY Id;",
+    );
+    for row in statement.iter() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        };
+        let mut rowids: Vec<i64> = row
+            .read::<&str, _>(1)
+            .split(',')
+            .filter_map(|rowid| rowid.parse().ok())
+            .collect();
+        rowids.sort();
+        // The oldest row keeps the id
+        rowids.remove(0);
+        problems.push(Problem::DuplicateId {
+            id: row.read::<i64, _>(0),
+            rowids,
+        });
+    }
+
+    for (table, columns) in SIDE_TABLES {
+        let orphaned = orphaned(columns);
+        let mut statement = prepare(
+            &db,
+            &format!("SELECT count(*) FROM {table} WHERE {orphaned};"),
+        );
+        let count = match statement.next() {
+            Ok(sqlite::State::Row) => statement.read::<i64, _>(0).unwrap(),
+            _ => fail!("Database Error: Could not count the rows in {table}!"),
+        };
+        if count > 0 {
+            problems.push(Problem::Orphans { table, count });
+        }
+    }
+    problems
+}
+
+/// Moves the entry in row `rowid` to the `Quarantine` table, noting `reason`
+pub fn quarantine_entry(rowid: i64, reason: &str) {
+    let db = open_db();
+    if let Err(e) = db.execute("BEGIN TRANSACTION;") {
+        fail!("DataBase Error: {e}");
+    }
+    let mut statement = prepare(
+        &db,
+        "INSERT INTO Quarantine SELECT ?, Id, TimeStamp, Title, Content, Notebook, Repo
+         FROM Entries WHERE rowid = ?;",
+    );
+    statement.bind((1, reason)).unwrap();
+    statement.bind((2, rowid)).unwrap();
+    run(statement);
+    let mut statement = prepare(&db, "DELETE FROM Entries WHERE rowid = ?;");
+    statement.bind((1, rowid)).unwrap();
+    run(statement);
+    if let Err(e) = db.execute("COMMIT;") {
+        fail!("DataBase Error: {e}");
+    }
 }
 
-fn execute<'a>(query: &'a str) {
+/// Gives the entry in row `rowid` an id no other entry, quarantined or not,
+/// has, returning it. If `move_rows` is set, the rows in other tables that
+/// belonged to its old id move to the new one; otherwise they stay with
+/// whichever entry still has the old id.
+pub fn renumber_entry(rowid: i64, move_rows: bool) -> u64 {
+    let id = get_latest_id() as i64 + 1;
     let db = open_db();
-    let status = db.execute(query);
-    if let Err(e) = status {
-        fail!("DataBase Error: {e}")
+    if let Err(e) = db.execute("BEGIN TRANSACTION;") {
+        fail!("DataBase Error: {e}");
+    }
+    if move_rows {
+        for (table, columns) in SIDE_TABLES {
+            for column in *columns {
+                let mut statement = prepare(
+                    &db,
+                    &format!(
+                        "UPDATE {table} SET {column} = ?
+                         WHERE {column} = (SELECT Id FROM Entries WHERE rowid = ?);"
+                    ),
+                );
+                statement.bind((1, id)).unwrap();
+                statement.bind((2, rowid)).unwrap();
+                run(statement);
+            }
+        }
+    }
+    let mut statement = prepare(&db, "UPDATE Entries SET Id = ? WHERE rowid = ?;");
+    statement.bind((1, id)).unwrap();
+    statement.bind((2, rowid)).unwrap();
+    run(statement);
+    if let Err(e) = db.execute("COMMIT;") {
+        fail!("DataBase Error: {e}");
     }
+    id as u64
 }
 
-/// Clears the database
-pub fn initialize_db() {
+/// Fills in a missing title, content or notebook of the entry in row `rowid`
+/// with an empty title or content, or the default notebook
+pub fn fill_in_entry(rowid: i64, column: &str) {
+    let value = match column {
+        "Notebook" => String::from(DEFAULT_NOTEBOOK),
+        _ => crypto::seal(crypto::cipher(), ""),
+    };
     let db = open_db();
-    let query = "DROP TABLE IF EXISTS Entries; CREATE TABLE IF NOT EXISTS Entries (Id INTEGER, TimeStamp TEXT, Title TEXT, Content TEXT);";
-    db.execute(query).expect("oopsie");
+    let mut statement = prepare(
+        &db,
+        &format!("UPDATE Entries SET {column} = ? WHERE rowid = ?;"),
+    );
+    statement.bind((1, value.as_str())).unwrap();
+    statement.bind((2, rowid)).unwrap();
+    run(statement);
+}
+
+/// Removes the rows of `table` that belong to no entry, returning how many
+/// there were
+pub fn remove_orphans(table: &str) -> u64 {
+    let Some((table, columns)) = SIDE_TABLES.iter().find(|(name, _)| *name == table) else {
+        fail!("Unknown table '{table}'");
+    };
+    let orphaned = orphaned(columns);
+    let db = open_db();
+    if let Err(e) = db.execute(format!("DELETE FROM {table} WHERE {orphaned};")) {
+        fail!("DataBase Error: {e}");
+    }
+    db.change_count() as u64
 }
 
 fn open_db() -> sqlite::Connection {
-    let connection = sqlite::open(format!("{}/code_diary.db", std::env::var("HOME").unwrap()));
+    let connection = sqlite::open(db_path());
     if connection.is_err() {
         fail!("Database Error!");
     }
     let connection = connection.unwrap();
     connection.execute("CREATE TABLE IF NOT EXISTS Entries (Id INTEGER, TimeStamp TEXT, Title TEXT, Content TEXT);").unwrap();
     connection
+        .execute("CREATE TABLE IF NOT EXISTS Settings (Key TEXT PRIMARY KEY, Value TEXT);")
+        .unwrap();
+    migrate(&connection);
+    connection
+}
+
+fn migrate(db: &sqlite::Connection) {
+    let mut statement = prepare(db, "PRAGMA user_version;");
+    let version = match statement.next() {
+        Ok(sqlite::State::Row) => statement.read::<i64, _>(0).unwrap() as usize,
+        _ => fail!("Database Error: Could not read the schema version!"),
+    };
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let query = format!(
+            "BEGIN TRANSACTION; {migration} PRAGMA user_version = {}; COMMIT;",
+            index + 1
+        );
+        if let Err(e) = db.execute(query) {
+            fail!("Database Error: Could not upgrade the database: {e}");
+        }
+    }
+}
+
+fn prepare<'a>(db: &'a sqlite::Connection, query: &str) -> sqlite::Statement<'a> {
+    match db.prepare(query) {
+        Ok(statement) => statement,
+        Err(e) => fail!("DataBase Error: {e}"),
+    }
+}
+
+fn run(mut statement: sqlite::Statement) {
+    if let Err(e) = statement.next() {
+        fail!("DataBase Error: {e}");
+    }
+}
+
+/// Reads a value from the `Settings` table, which holds diary-wide state such
+/// as whether the diary is encrypted.
+pub fn get_setting(key: &str) -> Option<String> {
+    let db = open_db();
+    let mut statement = prepare(&db, "SELECT Value FROM Settings WHERE Key = ?;");
+    statement.bind((1, key)).unwrap();
+    match statement.next() {
+        Ok(sqlite::State::Row) => statement.read::<Option<String>, _>(0).unwrap(),
+        Ok(sqlite::State::Done) => None,
+        Err(e) => fail!("DataBase Error: {e}"),
+    }
+}
+
+/// Writes a value to the `Settings` table, replacing any previous value.
+pub fn set_setting(key: &str, value: &str) {
+    write_setting(&open_db(), key, value);
+}
+
+fn write_setting(db: &sqlite::Connection, key: &str, value: &str) {
+    let mut statement = prepare(db, "INSERT OR REPLACE INTO Settings VALUES (?, ?);");
+    statement.bind(&[key, value][..]).unwrap();
+    run(statement);
+}
+
+/// Removes a value from the `Settings` table
+pub fn remove_setting(key: &str) {
+    let db = open_db();
+    let mut statement = prepare(&db, "DELETE FROM Settings WHERE Key = ?;");
+    statement.bind((1, key)).unwrap();
+    run(statement);
+}
+
+/// Selects the notebook that entries are read from and added to for the rest
+/// of the program.
+/// # Panics
+/// Will 'gracefully' exit if the notebook does not exist
+pub fn select_notebook(name: &str) {
+    if !get_notebooks().iter().any(|(notebook, _)| notebook == name) {
+        fail!(
+            "There is no notebook named '{name}'. Create it with `code_diary notebook new {name}`"
+        );
+    }
+    if NOTEBOOK.set(name.to_string()).is_err() {
+        fail!("A notebook has already been selected!");
+    }
+}
+
+/// Returns the selected notebook, or [`DEFAULT_NOTEBOOK`] if none was selected
+pub fn current_notebook() -> String {
+    NOTEBOOK
+        .get()
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_NOTEBOOK.to_string())
+}
+
+/// Creates an empty notebook
+/// # Panics
+/// Will 'gracefully' exit if the notebook already exists
+pub fn create_notebook(name: &str) {
+    if get_notebooks().iter().any(|(notebook, _)| notebook == name) {
+        fail!("The notebook '{name}' already exists!");
+    }
+    let db = open_db();
+    let mut statement = prepare(&db, "INSERT INTO Notebooks VALUES (?);");
+    statement.bind((1, name)).unwrap();
+    run(statement);
+}
+
+/// Returns every notebook along with how many entries it holds, sorted by name
+pub fn get_notebooks() -> Vec<(String, u64)> {
+    let db = open_db();
+    let mut statement = prepare(
+        &db,
+        "SELECT Notebooks.Name, COUNT(Entries.Id) FROM Notebooks
+         LEFT JOIN Entries ON Entries.Notebook = Notebooks.Name
+         GROUP BY Notebooks.Name ORDER BY Notebooks.Name;",
+    );
+    let mut notebooks = Vec::new();
+    for row in statement.iter() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        };
+        notebooks.push((
+            row.read::<&str, _>(0).to_string(),
+            row.read::<i64, _>(1) as u64,
+        ));
+    }
+    notebooks
+}
+
+/// Moves the entry with the given id into another notebook
+/// # Panics
+/// Will 'gracefully' exit if the notebook or entry does not exist
+pub fn move_entry(id: u64, notebook: &str) {
+    if !get_notebooks().iter().any(|(name, _)| name == notebook) {
+        fail!("There is no notebook named '{notebook}'");
+    }
+    let db = open_db();
+    let mut statement = prepare(&db, "UPDATE Entries SET Notebook = ? WHERE Id = ?;");
+    statement.bind((1, notebook)).unwrap();
+    statement.bind((2, id as i64)).unwrap();
+    run(statement);
+    if db.change_count() == 0 {
+        fail!("There is no entry with id {id}");
+    }
+}
+
+/// Pins or unpins the entry with the given id
+/// # Panics
+/// Will 'gracefully' exit if the entry does not exist
+pub fn set_pinned(id: u64, pinned: bool) {
+    set_flag(id, "Pinned", pinned);
+}
+
+/// Stars or unstars the entry with the given id
+/// # Panics
+/// Will 'gracefully' exit if the entry does not exist
+pub fn set_starred(id: u64, starred: bool) {
+    set_flag(id, "Starred", starred);
+}
+
+fn set_flag(id: u64, column: &str, value: bool) {
+    let db = open_db();
+    let mut statement = prepare(
+        &db,
+        &format!("UPDATE Entries SET {column} = ? WHERE Id = ?;"),
+    );
+    statement.bind((1, i64::from(value))).unwrap();
+    statement.bind((2, id as i64)).unwrap();
+    run(statement);
+    if db.change_count() == 0 {
+        fail!("There is no entry with id {id}");
+    }
 }
+
 /// Removes a selected ID from the entries database.
 /// # Panics
 /// Will 'gracefully' exit on a database error
@@ -107,13 +564,30 @@ fn open_db() -> sqlite::Connection {
 /// ```
 pub fn remove_id(id: u64) {
     let connection = open_db();
-    let query = format!("DELETE FROM Entries WHERE Id = {id};");
+    let query =
+    format!("DELETE FROM Entries WHERE Id = {id}; DELETE FROM Tags WHERE EntryId = {id}; DELETE FROM Commits WHERE EntryId = {id}; DELETE FROM Refs WHERE EntryId = {id}; DELETE FROM EntryLinks WHERE SourceId = {id} OR TargetId = {id}; DELETE FROM Attachments WHERE EntryId = {id}; DELETE FROM Sessions WHERE EntryId = {id}; DELETE FROM Fields WHERE EntryId = {id}; {DELETE_UNUSED_BLOBS}");
     let result = connection.execute(query);
     if let Err(e) = result {
         fail!("Could not remove entry: {e}");
     }
 }
 
+/// Same as [`remove_id`] but removes several entries in one transaction
+pub fn remove_ids(ids: &[u64]) {
+    let connection = open_db();
+    let ids = ids
+        .iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let query = format!(
+        "BEGIN TRANSACTION; DELETE FROM Entries WHERE Id IN ({ids}); DELETE FROM Tags WHERE EntryId IN ({ids}); DELETE FROM Commits WHERE EntryId IN ({ids}); DELETE FROM Refs WHERE EntryId IN ({ids}); DELETE FROM EntryLinks WHERE SourceId IN ({ids}) OR TargetId IN ({ids}); DELETE FROM Attachments WHERE EntryId IN ({ids}); DELETE FROM Sessions WHERE EntryId IN ({ids}); DELETE FROM Fields WHERE EntryId IN ({ids}); {DELETE_UNUSED_BLOBS} COMMIT;"
+    );
+    if let Err(e) = connection.execute(query) {
+        fail!("Could not remove entries: {e}");
+    }
+}
+
 /// Stores a `DiaryEntry`
 /// # Panics
 /// Will automatically 'gracefully' exit on a problem opening the DB
@@ -130,17 +604,526 @@ pub fn remove_id(id: u64) {
 /// assert_eq!(entry, get_entries()[0]);
 /// # }
 pub fn store_entry(entry: &DiaryEntry) {
-    //initialize_db();
-    let date = entry.clone().date;
-    let title = entry.clone().title.unwrap_or(String::from(""));
-    let content = entry.clone().content;
-    let id = entry.clone().id;
+    let db = open_db();
+    insert_entry(&db, crypto::cipher(), entry);
+    write_links(&db, entry);
+}
+
+fn insert_entry(db: &sqlite::Connection, cipher: Option<&XChaCha20Poly1305>, entry: &DiaryEntry) {
+    let title = crypto::seal(cipher, entry.title.as_deref().unwrap_or(""));
+    let content = crypto::seal(cipher, &entry.content);
+
+    let mut statement = prepare(
+        db,
+        "INSERT INTO Entries (Id, TimeStamp, Title, Content, Notebook, Repo, Pinned, Starred)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?);",
+    );
+    statement.bind((1, entry.id as i64)).unwrap();
+    statement
+        .bind((2, entry.date.to_string().as_str()))
+        .unwrap();
+    statement.bind((3, title.as_str())).unwrap();
+    statement.bind((4, content.as_str())).unwrap();
+    statement.bind((5, entry.notebook.as_str())).unwrap();
+    statement.bind((6, entry.repo.as_deref())).unwrap();
+    statement.bind((7, i64::from(entry.pinned))).unwrap();
+    statement.bind((8, i64::from(entry.starred))).unwrap();
+    run(statement);
+    write_tags(db, entry);
+    write_refs(db, entry);
+    write_attachments(db, cipher, entry);
+    write_fields(db, entry);
+    if let Some(ended) = &entry.ended {
+        let mut statement = prepare(db, "INSERT OR REPLACE INTO Sessions VALUES (?, ?);");
+        statement.bind((1, entry.id as i64)).unwrap();
+        statement.bind((2, ended.to_string().as_str())).unwrap();
+        run(statement);
+    }
+}
+
+fn write_tags(db: &sqlite::Connection, entry: &DiaryEntry) {
+    let mut statement = prepare(db, "DELETE FROM Tags WHERE EntryId = ?;");
+    statement.bind((1, entry.id as i64)).unwrap();
+    run(statement);
+
+    for tag in &entry.tags {
+        let mut statement = prepare(db, "INSERT OR IGNORE INTO Tags VALUES (?, ?);");
+        statement.bind((1, entry.id as i64)).unwrap();
+        statement.bind((2, tag.as_str())).unwrap();
+        run(statement);
+    }
+}
+
+fn write_refs(db: &sqlite::Connection, entry: &DiaryEntry) {
+    let mut statement = prepare(db, "DELETE FROM Refs WHERE EntryId = ?;");
+    statement.bind((1, entry.id as i64)).unwrap();
+    run(statement);
+
+    for file_ref in &entry.refs {
+        let mut statement = prepare(db, "INSERT INTO Refs VALUES (?, ?, ?, ?, ?, ?);");
+        let (first, last) = file_ref.lines.unzip();
+        statement.bind((1, entry.id as i64)).unwrap();
+        statement.bind((2, file_ref.path.as_str())).unwrap();
+        statement.bind((3, first.map(i64::from))).unwrap();
+        statement.bind((4, last.map(i64::from))).unwrap();
+        statement.bind((5, file_ref.root.as_deref())).unwrap();
+        statement.bind((6, file_ref.commit.as_deref())).unwrap();
+        run(statement);
+    }
+}
+
+fn write_fields(db: &sqlite::Connection, entry: &DiaryEntry) {
+    let mut statement = prepare(db, "DELETE FROM Fields WHERE EntryId = ?;");
+    statement.bind((1, entry.id as i64)).unwrap();
+    run(statement);
+
+    for (name, value) in &entry.fields {
+        let mut statement = prepare(db, "INSERT OR REPLACE INTO Fields VALUES (?, ?, ?);");
+        statement.bind((1, entry.id as i64)).unwrap();
+        statement.bind((2, name.as_str())).unwrap();
+        statement.bind((3, i64::from(*value))).unwrap();
+        run(statement);
+    }
+}
+
+fn write_attachments(
+    db: &sqlite::Connection,
+    cipher: Option<&XChaCha20Poly1305>,
+    entry: &DiaryEntry,
+) {
+    let mut statement = prepare(db, "DELETE FROM Attachments WHERE EntryId = ?;");
+    statement.bind((1, entry.id as i64)).unwrap();
+    run(statement);
+
+    for attachment in &entry.attachments {
+        let name = crypto::seal(cipher, &attachment.name);
+        let mut statement = prepare(db, "INSERT INTO Attachments VALUES (?, ?, ?, ?);");
+        statement.bind((1, entry.id as i64)).unwrap();
+        statement.bind((2, name.as_str())).unwrap();
+        statement.bind((3, attachment.hash.as_str())).unwrap();
+        statement.bind((4, attachment.size as i64)).unwrap();
+        run(statement);
+    }
+}
+
+/// Stores the contents of an attached file under its `hash`, unless they are
+/// already stored
+pub fn store_blob(hash: &str, data: &[u8]) {
+    let db = open_db();
+    let data = crypto::seal_bytes(crypto::cipher(), data);
+    let mut statement = prepare(&db, "INSERT OR IGNORE INTO Blobs VALUES (?, ?);");
+    statement.bind((1, hash)).unwrap();
+    statement.bind((2, data.as_slice())).unwrap();
+    run(statement);
+}
+
+/// Returns the contents of the attached file with `hash`, if they are stored
+pub fn get_blob(hash: &str) -> Option<Vec<u8>> {
+    let db = open_db();
+    let mut statement = prepare(&db, "SELECT Data FROM Blobs WHERE Hash = ?;");
+    statement.bind((1, hash)).unwrap();
+    match statement.next() {
+        Ok(sqlite::State::Row) => {
+            let data = statement.read::<Vec<u8>, _>(0);
+            let data = match data {
+                Ok(data) => data,
+                Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+            };
+            Some(crypto::open_bytes(crypto::cipher(), &data))
+        }
+        _ => None,
+    }
+}
+
+fn seal_attachments(db: &sqlite::Connection, cipher: &XChaCha20Poly1305, hash_key: &[u8; 32]) {
+    let mut blobs = Vec::new();
+    for row in prepare(db, "SELECT Hash, Data FROM Blobs;").iter() {
+        match row {
+            Ok(row) => blobs.push((
+                row.read::<&str, _>(0).to_string(),
+                row.read::<&[u8], _>(1).to_vec(),
+            )),
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        }
+    }
+    for (hash, data) in blobs {
+        let keyed = crypto::keyed_hash(hash_key, &data);
+        let mut statement = prepare(db, "UPDATE Blobs SET Hash = ?, Data = ? WHERE Hash = ?;");
+        statement.bind((1, keyed.as_str())).unwrap();
+        statement
+            .bind((2, crypto::seal_bytes(Some(cipher), &data).as_slice()))
+            .unwrap();
+        statement.bind((3, hash.as_str())).unwrap();
+        run(statement);
+        let mut statement = prepare(db, "UPDATE Attachments SET Hash = ? WHERE Hash = ?;");
+        statement.bind((1, keyed.as_str())).unwrap();
+        statement.bind((2, hash.as_str())).unwrap();
+        run(statement);
+    }
+
+    let mut names = Vec::new();
+    for row in prepare(db, "SELECT rowid, Name FROM Attachments;").iter() {
+        match row {
+            Ok(row) => names.push((row.read::<i64, _>(0), row.read::<&str, _>(1).to_string())),
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        }
+    }
+    for (rowid, name) in names {
+        let mut statement = prepare(db, "UPDATE Attachments SET Name = ? WHERE rowid = ?;");
+        statement
+            .bind((1, crypto::seal(Some(cipher), &name).as_str()))
+            .unwrap();
+        statement.bind((2, rowid)).unwrap();
+        run(statement);
+    }
+}
+
+/// Saves changes to the title, content and tags of an already stored entry
+/// # Panics
+/// Will 'gracefully' exit if there is no entry with the same id
+pub fn update_entry(entry: &DiaryEntry) {
+    let db = open_db();
+    let cipher = crypto::cipher();
+    let title = crypto::seal(cipher, entry.title.as_deref().unwrap_or(""));
+    let content = crypto::seal(cipher, &entry.content);
+
+    let mut statement = prepare(
+        &db,
+        "UPDATE Entries SET Title = ?, Content = ? WHERE Id = ?;",
+    );
+    statement.bind((1, title.as_str())).unwrap();
+    statement.bind((2, content.as_str())).unwrap();
+    statement.bind((3, entry.id as i64)).unwrap();
+    run(statement);
+    if db.change_count() == 0 {
+        let id = entry.id;
+        fail!("There is no entry with id {id}");
+    }
+    write_tags(&db, entry);
+    write_links(&db, entry);
+}
+
+/// Replaces the links from `entry` with the ones in its content, and relinks
+/// the entries mentioning it or linking to it
+fn write_links(db: &sqlite::Connection, entry: &DiaryEntry) {
+    let entries = linkable_entries(db);
+    for (source, targets) in links::relink(entry, &entries, &read_links(db)) {
+        replace_links(db, source, &targets);
+    }
+}
+
+/// Replaces the links from every entry with the ones in its content
+fn write_all_links(db: &sqlite::Connection) {
+    let entries = linkable_entries(db);
+    let targets: Vec<links::Target> = entries.iter().map(links::target).collect();
+    for entry in &entries {
+        replace_links(db, entry.id, &links::targets(entry, &targets));
+    }
+}
+
+/// Returns every entry with its id, title, notebook and content, which is
+/// all links need
+fn linkable_entries(db: &sqlite::Connection) -> Vec<DiaryEntry> {
+    // Rows without a usable id are left for `doctor`
+    let mut statement = prepare(
+        db,
+        "SELECT Id, Title, Notebook, Content FROM Entries WHERE typeof(Id) = 'integer';",
+    );
+    let cipher = crypto::cipher();
+    let mut entries: Vec<DiaryEntry> = Vec::new();
+    for row in statement.iter() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        };
+        let open = |column: usize| {
+            row.read::<Option<&str>, _>(column)
+                .map(|text| crypto::open(cipher, text))
+                .unwrap_or_default()
+        };
+        entries.push(DiaryEntry {
+            id: row.read::<i64, _>(0) as u64,
+            title: Some(open(1)),
+            notebook: row
+                .read::<Option<&str>, _>(2)
+                .unwrap_or(DEFAULT_NOTEBOOK)
+                .to_string(),
+            content: open(3),
+            ..Default::default()
+        });
+    }
+    entries
+}
+
+fn replace_links(db: &sqlite::Connection, source: u64, targets: &[u64]) {
+    let mut statement = prepare(db, "DELETE FROM EntryLinks WHERE SourceId = ?;");
+    statement.bind((1, source as i64)).unwrap();
+    run(statement);
+    for target in targets {
+        let mut statement = prepare(db, "INSERT OR IGNORE INTO EntryLinks VALUES (?, ?);");
+        statement.bind((1, source as i64)).unwrap();
+        statement.bind((2, *target as i64)).unwrap();
+        run(statement);
+    }
+}
+
+/// Returns every link between entries as the ids of the entry linking and
+/// the entry linked to
+pub fn get_links() -> Vec<(u64, u64)> {
+    read_links(&open_db())
+}
+
+fn read_links(db: &sqlite::Connection) -> Vec<(u64, u64)> {
+    let mut statement = prepare(
+        db,
+        "SELECT SourceId, TargetId FROM EntryLinks ORDER BY rowid;",
+    );
+    let mut links = Vec::new();
+    for row in statement.iter() {
+        match row {
+            Ok(row) => links.push((row.read::<i64, _>(0) as u64, row.read::<i64, _>(1) as u64)),
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        }
+    }
+    links
+}
+
+/// Returns the hashes of every commit imported so far, in any notebook
+pub fn get_imported_commits() -> HashSet<String> {
+    let db = open_db();
+    let mut statement = prepare(&db, "SELECT Hash FROM Commits;");
+    let mut hashes = HashSet::new();
+    for row in statement.iter() {
+        match row {
+            Ok(row) => hashes.insert(row.read::<&str, _>(0).to_string()),
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        };
+    }
+    hashes
+}
+
+/// Remembers that the commits with `hashes` were imported as the entry with
+/// id `entry_id`, so they are not imported again
+pub fn record_commits(entry_id: u64, hashes: &[String]) {
+    write_commits(&open_db(), entry_id, hashes);
+}
+
+/// Stores the entries made by `import --git` in one transaction, each along
+/// with the hashes of the commits it was made from, and links them once all
+/// are stored
+pub fn store_imported(entries: &[(DiaryEntry, Vec<String>)]) {
+    let db = open_db();
+    let cipher = crypto::cipher();
+    if let Err(e) = db.execute("BEGIN TRANSACTION;") {
+        fail!("DataBase Error: {e}");
+    }
+    for (entry, hashes) in entries {
+        insert_entry(&db, cipher, entry);
+        write_commits(&db, entry.id, hashes);
+    }
+    write_all_links(&db);
+    if let Err(e) = db.execute("COMMIT;") {
+        fail!("DataBase Error: {e}");
+    }
+}
+
+fn write_commits(db: &sqlite::Connection, entry_id: u64, hashes: &[String]) {
+    for hash in hashes {
+        let mut statement = prepare(db, "INSERT OR IGNORE INTO Commits VALUES (?, ?);");
+        statement.bind((1, hash.as_str())).unwrap();
+        statement.bind((2, entry_id as i64)).unwrap();
+        run(statement);
+    }
+}
+
+/// Returns every tag in the selected notebook along with how many entries
+/// carry it, sorted by name
+pub fn get_tags() -> Vec<(String, u64)> {
+    let db = open_db();
+    let mut statement = prepare(
+        &db,
+        "SELECT Tags.Tag, COUNT(*) FROM Tags JOIN Entries ON Entries.Id = Tags.EntryId
+         WHERE Entries.Notebook = ? GROUP BY Tags.Tag ORDER BY Tags.Tag;",
+    );
+    statement.bind((1, current_notebook().as_str())).unwrap();
+    let mut tags = Vec::new();
+    for row in statement.iter() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        };
+        tags.push((
+            row.read::<&str, _>(0).to_string(),
+            row.read::<i64, _>(1) as u64,
+        ));
+    }
+    tags
+}
+
+fn tags_by_entry(db: &sqlite::Connection) -> HashMap<u64, Vec<String>> {
+    let mut statement = prepare(db, "SELECT EntryId, Tag FROM Tags ORDER BY Tag;");
+    let mut tags: HashMap<u64, Vec<String>> = HashMap::new();
+    for row in statement.iter() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        };
+        tags.entry(row.read::<i64, _>(0) as u64)
+            .or_default()
+            .push(row.read::<&str, _>(1).to_string());
+    }
+    tags
+}
+
+fn refs_by_entry(db: &sqlite::Connection) -> HashMap<u64, Vec<FileRef>> {
+    let mut statement = prepare(
+        db,
+        "SELECT EntryId, Path, FirstLine, LastLine, Root, CommitHash FROM Refs ORDER BY rowid;",
+    );
+    let mut refs: HashMap<u64, Vec<FileRef>> = HashMap::new();
+    for row in statement.iter() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        };
+        let first = row.read::<Option<i64>, _>(2);
+        let last = row.read::<Option<i64>, _>(3);
+        refs.entry(row.read::<i64, _>(0) as u64)
+            .or_default()
+            .push(FileRef {
+                path: row.read::<&str, _>(1).to_string(),
+                lines: first
+                    .zip(last)
+                    .map(|(first, last)| (first as u32, last as u32)),
+                root: row.read::<Option<&str>, _>(4).map(String::from),
+                commit: row.read::<Option<&str>, _>(5).map(String::from),
+            });
+    }
+    refs
+}
+
+fn attachments_by_entry(
+    db: &sqlite::Connection,
+    cipher: Option<&XChaCha20Poly1305>,
+) -> HashMap<u64, Vec<Attachment>> {
+    let mut statement = prepare(
+        db,
+        "SELECT EntryId, Name, Hash, Size FROM Attachments ORDER BY rowid;",
+    );
+    let mut attachments: HashMap<u64, Vec<Attachment>> = HashMap::new();
+    for row in statement.iter() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        };
+        attachments
+            .entry(row.read::<i64, _>(0) as u64)
+            .or_default()
+            .push(Attachment {
+                name: crypto::open(cipher, row.read::<&str, _>(1)),
+                hash: row.read::<&str, _>(2).to_string(),
+                size: row.read::<i64, _>(3) as u64,
+            });
+    }
+    attachments
+}
 
-    let query = format!("INSERT INTO Entries VALUES ({id}, '{date}', '{title}', '{content}');");
-    execute(&query);
+fn fields_by_entry(db: &sqlite::Connection) -> HashMap<u64, Vec<(String, u8)>> {
+    let mut statement = prepare(
+        db,
+        "SELECT EntryId, Name, Value FROM Fields ORDER BY rowid;",
+    );
+    let mut fields: HashMap<u64, Vec<(String, u8)>> = HashMap::new();
+    for row in statement.iter() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        };
+        fields
+            .entry(row.read::<i64, _>(0) as u64)
+            .or_default()
+            .push((
+                row.read::<&str, _>(1).to_string(),
+                row.read::<i64, _>(2) as u8,
+            ));
+    }
+    fields
+}
+
+fn session_ends(db: &sqlite::Connection) -> HashMap<u64, TimeStamp> {
+    let mut statement = prepare(db, "SELECT EntryId, End FROM Sessions;");
+    let mut ends = HashMap::new();
+    for row in statement.iter() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        };
+        if let Some(end) = TimeStamp::from_string(row.read::<&str, _>(1)) {
+            ends.insert(row.read::<i64, _>(0) as u64, end);
+        }
+    }
+    ends
+}
+
+/// Turns on encryption for an existing diary in one transaction: the title
+/// and content of every stored entry, in every notebook, are sealed with
+/// `cipher` where they are, every attached file and its name is sealed and
+/// rehashed with `hash_key`, and `settings` are written, including the one that marks the diary as encrypted. Entries are
+/// sealed even if they cannot be read otherwise, so `doctor` can still repair
+/// them. If anything fails, the diary is left unencrypted. Returns how many
+/// entries were sealed.
+pub fn encrypt_diary(
+    cipher: &XChaCha20Poly1305,
+    hash_key: &[u8; 32],
+    settings: &[(&str, &str)],
+) -> u64 {
+    let db = open_db();
+    if let Err(e) = db.execute("BEGIN TRANSACTION;") {
+        fail!("DataBase Error: {e}");
+    }
+    let mut rows = Vec::new();
+    let mut statement = prepare(
+        &db,
+        "SELECT rowid, CAST(Title AS TEXT), CAST(Content AS TEXT) FROM Entries;",
+    );
+    for row in statement.iter() {
+        match row {
+            Ok(row) => rows.push((
+                row.read::<i64, _>(0),
+                row.read::<Option<&str>, _>(1).map(String::from),
+                row.read::<Option<&str>, _>(2).map(String::from),
+            )),
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        }
+    }
+    for (rowid, title, content) in &rows {
+        // Missing titles and contents are left for `doctor`
+        let title = title
+            .as_deref()
+            .map(|title| crypto::seal(Some(cipher), title));
+        let content = content
+            .as_deref()
+            .map(|content| crypto::seal(Some(cipher), content));
+        let mut statement = prepare(
+            &db,
+            "UPDATE Entries SET Title = ?, Content = ? WHERE rowid = ?;",
+        );
+        statement.bind((1, title.as_deref())).unwrap();
+        statement.bind((2, content.as_deref())).unwrap();
+        statement.bind((3, *rowid)).unwrap();
+        run(statement);
+    }
+    seal_attachments(&db, cipher, hash_key);
+    for (key, value) in settings {
+        write_setting(&db, key, value);
+    }
+    if let Err(e) = db.execute("COMMIT;") {
+        fail!("DataBase Error: {e}");
+    }
+    rows.len() as u64
 }
 
-/// Returns A Vector of `DiaryEntry`s from the database
+/// Returns A Vector of `DiaryEntry`s in the selected notebook from the database
 /// # Panics
 /// Will automatically 'gracefully' exit if a problem is encountered
 /// # Examples
@@ -156,40 +1139,114 @@ pub fn store_entry(entry: &DiaryEntry) {
 /// # }
 /// ```
 pub fn get_entries() -> Vec<DiaryEntry> {
-    let ids = iter!("Id")
-        .iter()
-        .map(|x| x.parse::<u64>().unwrap())
-        .collect::<Vec<u64>>();
-    let dates = iter!("TimeStamp")
-        .iter()
-        .map(|x| TimeStamp::from_string(x).unwrap())
-        .collect::<Vec<TimeStamp>>();
-    let titles = iter!("Title");
-    let contents = iter!("Content");
+    query_entries(Some(&current_notebook()), None)
+}
+
+/// Same as [`get_entries`] but across every notebook
+pub fn get_all_entries() -> Vec<DiaryEntry> {
+    query_entries(None, None)
+}
+
+/// Same as [`get_entries`] but only the entries written on `date`
+pub fn get_entries_on(date: NaiveDate) -> Vec<DiaryEntry> {
+    // Stored timestamps are not zero-padded, see `TimeStamp`'s `Display`
+    let day = format!("{}-{}-{} %", date.year(), date.month(), date.day());
+    query_entries(Some(&current_notebook()), Some(&day))
+}
 
-    if !(dates.len() == titles.len() && titles.len() == contents.len()) {
-        fail!("Something went wrong!");
+/// Returns the id and date of every entry in the selected notebook, without
+/// decrypting anything
+/// # Panics
+/// Will 'gracefully' exit if the database cannot be read
+pub fn get_entry_dates() -> Vec<(u64, TimeStamp)> {
+    let db = open_db();
+    let mut statement = prepare(&db, "SELECT Id, TimeStamp FROM Entries WHERE Notebook = ?;");
+    statement.bind((1, current_notebook().as_str())).unwrap();
+    let mut dates = Vec::new();
+    for row in statement.iter() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
+        };
+        let id = row.try_read::<Option<i64>, _>(0).ok().flatten();
+        let date = row.try_read::<Option<&str>, _>(1).ok().flatten();
+        if let (Some(id), Some(date)) = (id, date.and_then(TimeStamp::from_string)) {
+            dates.push((id as u64, date));
+        }
     }
+    dates
+}
 
-    let mut results: Vec<DiaryEntry> = Vec::new();
+fn query_entries(notebook: Option<&str>, timestamp: Option<&str>) -> Vec<DiaryEntry> {
+    let db = open_db();
+    let cipher = crypto::cipher();
+    let mut tags = tags_by_entry(&db);
+    let mut refs = refs_by_entry(&db);
+    let mut attachments = attachments_by_entry(&db, cipher);
+    let mut ends = session_ends(&db);
+    let mut fields = fields_by_entry(&db);
+    let mut statement = prepare(
+        &db,
+        "SELECT Id, TimeStamp, Title, Content, Notebook, Repo, Pinned, Starred FROM Entries
+         WHERE (?1 IS NULL OR Notebook = ?1) AND (?2 IS NULL OR TimeStamp LIKE ?2);",
+    );
+    statement.bind((1, notebook)).unwrap();
+    statement.bind((2, timestamp)).unwrap();
 
-    for index in 0..dates.len() {
-        let id = ids[index].clone();
-        let date = dates[index].clone();
-        let content = contents[index].clone();
+    let mut results: Vec<DiaryEntry> = Vec::new();
+    let mut unreadable = 0;
 
-        let title = if titles[index] == "".to_string() {
-            None
-        } else {
-            Some(titles[index].clone())
+    for row in statement.iter() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => fail!("Database Error: Could not read value from DB! {e}"),
         };
+        // Rows without a usable id or timestamp are left for `doctor`
+        let id = row.try_read::<Option<i64>, _>("Id").ok().flatten();
+        let date = row
+            .try_read::<Option<&str>, _>("TimeStamp")
+            .ok()
+            .flatten()
+            .and_then(TimeStamp::from_string);
+        let (Some(id), Some(date)) = (id, date) else {
+            unreadable += 1;
+            continue;
+        };
+        let id = id as u64;
+        let read_sealed = |column: &str| match row.read::<Option<&str>, _>(column) {
+            Some(text) => crypto::open(cipher, text),
+            None => String::new(),
+        };
+        let title = read_sealed("Title");
+        let content = read_sealed("Content");
+        let notebook = row
+            .read::<Option<&str>, _>("Notebook")
+            .unwrap_or(DEFAULT_NOTEBOOK)
+            .to_string();
+        let repo = row.read::<Option<&str>, _>("Repo").map(String::from);
+        let pinned = row.read::<i64, _>("Pinned") != 0;
+        let starred = row.read::<i64, _>("Starred") != 0;
+
+        let title = if title.is_empty() { None } else { Some(title) };
 
         results.push(DiaryEntry {
             id,
             date,
             title,
             content,
+            notebook,
+            tags: tags.remove(&id).unwrap_or_default(),
+            repo,
+            refs: refs.remove(&id).unwrap_or_default(),
+            attachments: attachments.remove(&id).unwrap_or_default(),
+            ended: ends.remove(&id),
+            fields: fields.remove(&id).unwrap_or_default(),
+            pinned,
+            starred,
         })
     }
+    if unreadable > 0 {
+        eprintln!("Skipped {unreadable} entries that could not be read; run `code_diary doctor` to find out why");
+    }
     results
 }
@@ -0,0 +1,205 @@
+//! Copies of the database, made by `code_diary backup` and automatically
+//! before commands that could lose entries, and put back by `code_diary
+//! restore <file>`. Backups are named after when they were taken, so once
+//! there are more than `backup_keep` of them the oldest are removed.
+//! Automatic backups go in their own directory with their own limit,
+//! `auto_backup_keep`, so they never push out backups made on purpose.
+
+use std::path::{Path, PathBuf};
+
+use crate::backend;
+use crate::config::Config;
+use crate::fail;
+
+/// The start of every backup's file name
+const PREFIX: &str = "code_diary-";
+
+/// The extension of every backup's file name
+const EXTENSION: &str = ".db";
+
+/// The directory, inside the backup directory, holding automatic backups
+const AUTOMATIC_DIR: &str = "automatic";
+
+/// Backs up the database into `dir`, then removes all but the newest `keep`
+/// backups there. Returns the path of the new backup.
+/// # Panics
+/// Will 'gracefully' exit if the backup cannot be written
+pub fn create(dir: &Path, keep: usize) -> PathBuf {
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        let dir = dir.display();
+        fail!("Could not create {dir}: {e}");
+    }
+    let taken = chrono::Local::now().format("%Y%m%d-%H%M%S-%3f");
+    let path = dir.join(format!("{PREFIX}{taken}{EXTENSION}"));
+    backend::snapshot(&path);
+    rotate(dir, keep);
+    path
+}
+
+/// Backs up the database before a command that could lose entries, to the
+/// automatic backups directory of `config`. Returns the path of the backup,
+/// or `None` if there is no database yet.
+pub fn automatic(config: &Config) -> Option<PathBuf> {
+    if !backend::db_path().exists() {
+        return None;
+    }
+    let dir = config.backup_dir().join(AUTOMATIC_DIR);
+    Some(create(&dir, config.auto_backup_keep()))
+}
+
+/// Returns every backup in the backup directory of `config`, those taken on
+/// purpose first and then the automatic ones, oldest first
+pub fn all(config: &Config) -> Vec<PathBuf> {
+    let dir = config.backup_dir();
+    let mut backups = list(&dir);
+    backups.extend(list(&dir.join(AUTOMATIC_DIR)));
+    backups
+}
+
+/// Returns the backups in `dir`, oldest first
+pub fn list(dir: &Path) -> Vec<PathBuf> {
+    let Ok(files) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<PathBuf> = files
+        .filter_map(|file| file.ok())
+        .map(|file| file.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(PREFIX) && name.ends_with(EXTENSION))
+        })
+        .collect();
+    backups.sort();
+    backups
+}
+
+fn rotate(dir: &Path, keep: usize) {
+    let backups = list(dir);
+    for old in &backups[..backups.len().saturating_sub(keep)] {
+        if let Err(e) = std::fs::remove_file(old) {
+            let old = old.display();
+            fail!("Could not remove the old backup {old}: {e}");
+        }
+    }
+}
+
+/// Checks that `path` holds an intact diary that can be restored
+/// # Panics
+/// Will 'gracefully' exit if it does not
+pub fn check(path: &Path) {
+    if !path.is_file() {
+        let path = path.display();
+        fail!("{path} does not exist");
+    }
+    if let Err(problem) = backend::check_database(path) {
+        let path = path.display();
+        fail!("{path} is not an intact diary: {problem}");
+    }
+}
+
+/// Replaces the database with the copy at `path`, which should have passed
+/// [`check`]. The current database is backed up first, as set up in
+/// `config`, and the path of that backup is returned.
+/// # Panics
+/// Will 'gracefully' exit if the copy cannot be put in place
+pub fn restore(path: &Path, config: &Config) -> Option<PathBuf> {
+    let previous = automatic(config);
+    let db_path = backend::db_path();
+    // Copy next to the database first, so it is swapped in all at once
+    let staging = db_path.with_extension("restoring");
+    if let Err(e) = std::fs::copy(path, &staging) {
+        let staging = staging.display();
+        fail!("Could not write {staging}: {e}");
+    }
+    if let Err(e) = std::fs::rename(&staging, &db_path) {
+        let db_path = db_path.display();
+        fail!("Could not replace {db_path}: {e}");
+    }
+    previous
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(dir: &Path, names: &[&str]) {
+        for name in names {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+    }
+
+    fn names(paths: &[PathBuf]) -> Vec<String> {
+        paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn list_finds_only_backups_oldest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(
+            dir.path(),
+            &[
+                "code_diary-20230102-090000-000.db",
+                "code_diary-20221231-235959-999.db",
+                "code_diary-20230101-120000-000.db.tmp",
+                "notes.db",
+            ],
+        );
+        assert_eq!(
+            names(&list(dir.path())),
+            vec![
+                "code_diary-20221231-235959-999.db",
+                "code_diary-20230102-090000-000.db",
+            ]
+        );
+        assert!(list(&dir.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn rotate_keeps_the_newest_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(
+            dir.path(),
+            &[
+                "code_diary-20230101-090000-000.db",
+                "code_diary-20230102-090000-000.db",
+                "code_diary-20230103-090000-000.db",
+                "notes.db",
+            ],
+        );
+        rotate(dir.path(), 5);
+        assert_eq!(list(dir.path()).len(), 3);
+        rotate(dir.path(), 2);
+        assert_eq!(
+            names(&list(dir.path())),
+            vec![
+                "code_diary-20230102-090000-000.db",
+                "code_diary-20230103-090000-000.db",
+            ]
+        );
+        assert!(dir.path().join("notes.db").exists());
+    }
+
+    #[test]
+    fn all_lists_backups_made_on_purpose_then_automatic_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let automatic = dir.path().join(AUTOMATIC_DIR);
+        std::fs::create_dir(&automatic).unwrap();
+        touch(dir.path(), &["code_diary-20230102-090000-000.db"]);
+        touch(&automatic, &["code_diary-20230101-090000-000.db"]);
+        let config = Config {
+            backup_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        assert_eq!(
+            all(&config),
+            vec![
+                dir.path().join("code_diary-20230102-090000-000.db"),
+                automatic.join("code_diary-20230101-090000-000.db"),
+            ]
+        );
+    }
+}
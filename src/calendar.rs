@@ -0,0 +1,168 @@
+//! A month at a glance for `code_diary calendar`: a grid of days with the ones
+//! that have entries highlighted, and how many entries each has.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, NaiveDate};
+
+use crate::diaryentry::DiaryEntry;
+use crate::theme::{self, paint, Role};
+
+/// How many columns each day takes up in the grid
+const CELL_WIDTH: usize = 6;
+const WEEKDAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+/// Parses a `YYYY-MM` month into its first day
+pub fn parse_month(month: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d").ok()
+}
+
+/// Parses the day of `month` given as a day of the month (`14`) or a full
+/// `YYYY-MM-DD` date
+pub fn parse_day(day: &str, month: NaiveDate) -> Option<NaiveDate> {
+    match day.parse::<u32>() {
+        Ok(day) => month.with_day(day),
+        Err(_) => NaiveDate::parse_from_str(day, "%Y-%m-%d").ok(),
+    }
+}
+
+/// Returns whether `entry` was written on `day`
+pub fn written_on(entry: &DiaryEntry, day: NaiveDate) -> bool {
+    entry.date.year as i32 == day.year()
+        && entry.date.month as u32 == day.month()
+        && entry.date.day as u32 == day.day()
+}
+
+/// Counts the entries written on each day of `month`
+pub fn entries_per_day(entries: &[DiaryEntry], month: NaiveDate) -> BTreeMap<NaiveDate, usize> {
+    let mut days = BTreeMap::new();
+    for entry in entries {
+        if entry.date.year as i32 != month.year() || entry.date.month as u32 != month.month() {
+            continue;
+        }
+        if let Some(day) = month.with_day(entry.date.day as u32) {
+            *days.entry(day).or_insert(0) += 1;
+        }
+    }
+    days
+}
+
+/// Draws `month` as a grid of weeks starting on Monday, with the days in
+/// `counts` highlighted and `today` underlined
+pub fn show_month(
+    month: NaiveDate,
+    counts: &BTreeMap<NaiveDate, usize>,
+    today: NaiveDate,
+) -> String {
+    let width = CELL_WIDTH * WEEKDAYS.len();
+    let title = month.format("%B %Y").to_string();
+    let title = format!("{title:^width$}");
+    let mut lines = vec![paint(Role::Heading, title.trim_end())];
+    let header: String = WEEKDAYS
+        .iter()
+        .map(|weekday| format!("{weekday:>3}   "))
+        .collect();
+    lines.push(paint(Role::Muted, header.trim_end()));
+
+    let mut line = " ".repeat(CELL_WIDTH * month.weekday().num_days_from_monday() as usize);
+    let mut day = month;
+    while day.month() == month.month() {
+        line.push_str(&cell(day, counts.get(&day).copied().unwrap_or(0), today));
+        if day.weekday().num_days_from_monday() == 6 {
+            lines.push(std::mem::take(&mut line).trim_end().to_string());
+        }
+        day += Duration::days(1);
+    }
+    if !line.trim().is_empty() {
+        lines.push(line.trim_end().to_string());
+    }
+
+    let total: usize = counts.values().sum();
+    lines.push(String::new());
+    lines.push(match (total, counts.len()) {
+        (0, _) => String::from("No entries this month"),
+        (1, _) => String::from("1 entry on 1 day"),
+        (total, 1) => format!("{total} entries on 1 day"),
+        (total, days) => format!("{total} entries on {days} days"),
+    });
+    lines.join("\n")
+}
+
+fn cell(day: NaiveDate, count: usize, today: NaiveDate) -> String {
+    let mut codes = Vec::new();
+    if count > 0 {
+        codes.push(theme::codes(Role::Accent));
+    }
+    if day == today {
+        codes.push("4");
+    }
+    let number = theme::sgr(&codes, &format!("{:>3}", day.day()));
+    match count {
+        0 => format!("{number}   "),
+        count => format!(
+            "{number}{}",
+            paint(Role::Muted, &format!("{:<3}", format!("·{count}")))
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn entries_per_day_counts_only_the_month() {
+        let entries = [
+            DiaryEntry::at("2022-02-28 23:59:59"),
+            DiaryEntry::at("2022-03-01 00:00:00"),
+            DiaryEntry::at("2022-03-01 18:30:00"),
+            DiaryEntry::at("2022-03-31 12:00:00"),
+            DiaryEntry::at("2023-03-15 12:00:00"),
+            DiaryEntry::at("2022-04-01 00:00:00"),
+        ];
+        let counts = entries_per_day(&entries, date("2022-03-01"));
+        let expected = BTreeMap::from([(date("2022-03-01"), 2), (date("2022-03-31"), 1)]);
+        assert_eq!(counts, expected);
+        assert!(entries_per_day(&entries, date("2022-05-01")).is_empty());
+    }
+
+    #[test]
+    fn show_month_totals_entries_and_days() {
+        theme::init("dark", Some("never"));
+        let month = date("2022-03-01");
+        let today = date("2022-03-20");
+        let summary = |counts: &BTreeMap<NaiveDate, usize>| {
+            show_month(month, counts, today)
+                .lines()
+                .last()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(summary(&BTreeMap::new()), "No entries this month");
+        let one = BTreeMap::from([(date("2022-03-02"), 1)]);
+        assert_eq!(summary(&one), "1 entry on 1 day");
+        let busy = BTreeMap::from([(date("2022-03-02"), 3)]);
+        assert_eq!(summary(&busy), "3 entries on 1 day");
+        let several = BTreeMap::from([(date("2022-03-02"), 3), (date("2022-03-31"), 1)]);
+        assert_eq!(summary(&several), "4 entries on 2 days");
+    }
+
+    #[test]
+    fn show_month_starts_weeks_on_monday() {
+        theme::init("dark", Some("never"));
+        // 1 March 2022 was a Tuesday
+        let counts = BTreeMap::from([(date("2022-03-01"), 2)]);
+        let shown = show_month(date("2022-03-01"), &counts, date("2022-04-01"));
+        let lines: Vec<&str> = shown.lines().collect();
+        assert_eq!(lines[0].trim(), "March 2022");
+        assert_eq!(
+            lines[2],
+            format!("{}  1·2   2     3     4     5     6", " ".repeat(6))
+        );
+        assert_eq!(lines[6].trim_start(), "28    29    30    31");
+    }
+}
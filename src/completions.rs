@@ -0,0 +1,1089 @@
+//! Shell completion scripts for bash, zsh and fish, printed by
+//! `code_diary completions <shell>`.
+//!
+//! The scripts are generated from [`COMMANDS`], which mirrors the subcommands
+//! and options on `Cli`. Entry ids, tags, notebooks and settings are not known
+//! ahead of time, so the scripts ask the binary for them through the hidden
+//! `completions --candidates` option, which [`print_candidates`] answers.
+
+use std::cmp::Reverse;
+
+use crate::backend::{get_entries, get_entry_dates, get_notebooks, get_tags};
+use crate::config::{self, Config};
+use crate::{crypto, fail, report, templates};
+
+/// The shells there are completion scripts for
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// What can be completed for an option's value or a positional argument
+enum Value {
+    /// The option is a switch and takes no value
+    Switch,
+    /// Anything, so nothing is suggested
+    Text,
+    /// Entry ids, described by their titles
+    Ids,
+    /// Tags in the selected notebook
+    Tags,
+    /// Notebook names
+    Notebooks,
+    /// Setting names
+    Settings,
+    /// Report template names
+    Templates,
+    /// Entry template names
+    EntryTemplates,
+    /// The configured rating fields
+    Fields,
+    /// A file on disk
+    Files,
+    /// A directory on disk
+    Directories,
+    /// One of a fixed list of words
+    Choices(&'static [&'static str]),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Opt {
+    long: &'static str,
+    short: Option<char>,
+    value: Value,
+    help: &'static str,
+}
+
+#[derive(Debug)]
+struct Command {
+    /// The subcommand names leading to this command, separated by spaces; the
+    /// top level is `""`
+    path: &'static str,
+    help: &'static str,
+    options: &'static [Opt],
+    positionals: &'static [Value],
+    /// Whether the last positional argument can be repeated
+    many: bool,
+}
+
+const fn opt(long: &'static str, short: Option<char>, value: Value, help: &'static str) -> Opt {
+    Opt {
+        long,
+        short,
+        value,
+        help,
+    }
+}
+
+const fn command(
+    path: &'static str,
+    help: &'static str,
+    options: &'static [Opt],
+    positionals: &'static [Value],
+) -> Command {
+    Command {
+        path,
+        help,
+        options,
+        positionals,
+        many: false,
+    }
+}
+
+const SELECTION_DATES: [Opt; 2] = [
+    opt(
+        "before",
+        None,
+        Value::Text,
+        "only entries written before this date",
+    ),
+    opt(
+        "since",
+        None,
+        Value::Text,
+        "only entries written on or after this date",
+    ),
+];
+
+/// Every command and its options. Keep in sync with `Cli` in `main.rs`.
+const COMMANDS: &[Command] = &[
+    command(
+        "",
+        "Interact with diary entries",
+        &[
+            opt(
+                "notebook",
+                Some('n'),
+                Value::Notebooks,
+                "the notebook to use",
+            ),
+            opt(
+                "no-pager",
+                None,
+                Value::Switch,
+                "print long output directly instead of through $PAGER",
+            ),
+            opt(
+                "color",
+                None,
+                Value::Choices(&["auto", "always", "never"]),
+                "when to use colors",
+            ),
+        ],
+        &[],
+    ),
+    command(
+        "add",
+        "Add an entry",
+        &[
+            opt("title", Some('t'), Value::Text, "the title"),
+            opt("content", Some('c'), Value::Text, "the content"),
+            opt("tag", None, Value::Tags, "a tag to label the entry with"),
+            opt(
+                "template",
+                None,
+                Value::EntryTemplates,
+                "start from the named template",
+            ),
+            opt(
+                "ref",
+                None,
+                Value::Files,
+                "a file or lines the entry is about",
+            ),
+            opt(
+                "attach",
+                None,
+                Value::Files,
+                "a file to keep with the entry",
+            ),
+            opt("field", None, Value::Text, "a rating, e.g. mood=4"),
+        ],
+        &[],
+    ),
+    command("attachment", "Read the files kept with entries", &[], &[]),
+    command(
+        "attachment get",
+        "Write a file attached to an entry to disk",
+        &[opt(
+            "output",
+            Some('o'),
+            Value::Files,
+            "where to write it, or - for standard output",
+        )],
+        &[Value::Ids, Value::Text],
+    ),
+    command(
+        "backup",
+        "Back up the diary",
+        &[opt("keep", None, Value::Text, "how many backups to keep")],
+        &[],
+    ),
+    command(
+        "calendar",
+        "Show a month of entries",
+        &[opt(
+            "day",
+            Some('d'),
+            Value::Text,
+            "list the entries of this day",
+        )],
+        &[Value::Text],
+    ),
+    command(
+        "completions",
+        "Print a shell completion script",
+        &[],
+        &[Value::Choices(SHELLS)],
+    ),
+    command("config", "Manage the configuration file", &[], &[]),
+    command("config get", "Print a setting", &[], &[Value::Settings]),
+    command(
+        "config set",
+        "Change a setting",
+        &[],
+        &[Value::Settings, Value::Text],
+    ),
+    command("config list", "Print every setting", &[], &[]),
+    Command {
+        many: true,
+        ..command(
+            "delete",
+            "Delete entries",
+            &[
+                opt(
+                    "tag",
+                    None,
+                    Value::Tags,
+                    "only delete entries with this tag",
+                ),
+                SELECTION_DATES[0],
+                SELECTION_DATES[1],
+                opt(
+                    "yes",
+                    Some('y'),
+                    Value::Switch,
+                    "do not ask for confirmation",
+                ),
+            ],
+            &[Value::Ids],
+        )
+    },
+    command(
+        "doctor",
+        "Check the database for problems",
+        &[opt(
+            "fix",
+            None,
+            Value::Switch,
+            "repair what can be repaired",
+        )],
+        &[],
+    ),
+    command(
+        "edit",
+        "Edit an entry",
+        &[
+            opt("title", Some('t'), Value::Text, "the new title"),
+            opt("content", Some('c'), Value::Text, "the new content"),
+        ],
+        &[Value::Ids],
+    ),
+    command(
+        "hook",
+        "Record entries about commits from a git hook",
+        &[],
+        &[],
+    ),
+    command(
+        "hook install",
+        "Add the post-commit hook to this repository",
+        &[opt(
+            "auto",
+            None,
+            Value::Switch,
+            "record every commit without asking",
+        )],
+        &[],
+    ),
+    command("hook uninstall", "Remove the post-commit hook", &[], &[]),
+    command(
+        "hook run",
+        "Record an entry about the latest commit",
+        &[opt(
+            "auto",
+            None,
+            Value::Switch,
+            "record the entry without asking",
+        )],
+        &[],
+    ),
+    command(
+        "import",
+        "Create entries from the commits in a git repository",
+        &[
+            opt(
+                "git",
+                None,
+                Value::Directories,
+                "the repository to import the history of",
+            ),
+            opt("author", None, Value::Text, "only commits by this author"),
+            opt("since", None, Value::Text, "only commits from this date on"),
+            opt(
+                "before",
+                None,
+                Value::Text,
+                "only commits from before this date",
+            ),
+            opt(
+                "group",
+                None,
+                Value::Choices(&["commit", "day"]),
+                "make an entry per commit or per day",
+            ),
+        ],
+        &[],
+    ),
+    command(
+        "init",
+        "Set up the diary",
+        &[
+            opt(
+                "encrypt",
+                None,
+                Value::Switch,
+                "encrypt entries with a passphrase",
+            ),
+            opt(
+                "cache-key",
+                None,
+                Value::Switch,
+                "keep the unlocked key in the session keyring",
+            ),
+        ],
+        &[],
+    ),
+    command(
+        "links",
+        "Show the entries linked to an entry",
+        &[opt(
+            "depth",
+            Some('d'),
+            Value::Text,
+            "how many links away to follow",
+        )],
+        &[Value::Ids],
+    ),
+    command(
+        "list",
+        "List the newest entries",
+        &[
+            opt("limit", Some('l'), Value::Text, "how many entries to show"),
+            opt("all", Some('a'), Value::Switch, "show every entry"),
+            opt("starred", None, Value::Switch, "only show starred entries"),
+        ],
+        &[],
+    ),
+    command("notebook", "Manage notebooks", &[], &[]),
+    command("notebook new", "Create a notebook", &[], &[Value::Text]),
+    command("notebook list", "List notebooks", &[], &[]),
+    command(
+        "notebook move",
+        "Move an entry to another notebook",
+        &[],
+        &[Value::Ids, Value::Notebooks],
+    ),
+    command(
+        "pin",
+        "Keep an entry at the top of the list",
+        &[opt("undo", None, Value::Switch, "unpin the entry instead")],
+        &[Value::Ids],
+    ),
+    command(
+        "report",
+        "Summarize recent entries",
+        &[
+            opt(
+                "since",
+                None,
+                Value::Choices(&["today", "yesterday"]),
+                "only entries written on or after this date",
+            ),
+            opt(
+                "before",
+                None,
+                Value::Text,
+                "only entries written before this date",
+            ),
+            opt("week", None, Value::Switch, "entries written this week"),
+            opt("tag", None, Value::Tags, "only entries with this tag"),
+            opt(
+                "by",
+                None,
+                Value::Choices(&["day", "repo", "tag"]),
+                "group entries by day, repo or tag",
+            ),
+            opt("format", Some('f'), Value::Templates, "the template"),
+        ],
+        &[],
+    ),
+    command(
+        "restore",
+        "Replace the diary with a backup",
+        &[opt(
+            "yes",
+            Some('y'),
+            Value::Switch,
+            "do not ask for confirmation",
+        )],
+        &[Value::Files],
+    ),
+    command(
+        "show-ref",
+        "Print the code an entry refers to",
+        &[],
+        &[Value::Ids],
+    ),
+    Command {
+        many: true,
+        ..command(
+            "start",
+            "Start a work session",
+            &[opt(
+                "tag",
+                None,
+                Value::Tags,
+                "a tag to label the entry with",
+            )],
+            &[Value::Text],
+        )
+    },
+    command(
+        "star",
+        "Mark an entry as important",
+        &[opt("undo", None, Value::Switch, "unstar the entry instead")],
+        &[Value::Ids],
+    ),
+    command(
+        "stats",
+        "Show statistics about entries",
+        &[opt(
+            "field",
+            None,
+            Value::Fields,
+            "chart a rating over time",
+        )],
+        &[],
+    ),
+    command(
+        "stop",
+        "Stop the work session and write an entry about it",
+        &[opt(
+            "content",
+            Some('c'),
+            Value::Text,
+            "notes on the session",
+        )],
+        &[],
+    ),
+    Command {
+        many: true,
+        ..command(
+            "tag",
+            "Add or remove tags on entries",
+            &[
+                opt("add", None, Value::Tags, "a tag to add"),
+                opt("remove", None, Value::Tags, "a tag to remove"),
+                opt("tag", None, Value::Tags, "only retag entries with this tag"),
+                SELECTION_DATES[0],
+                SELECTION_DATES[1],
+            ],
+            &[Value::Ids],
+        )
+    },
+    command("template", "Manage entry templates", &[], &[]),
+    command("template list", "List templates", &[], &[]),
+    command("template new", "Create a template", &[], &[Value::Text]),
+    command(
+        "template edit",
+        "Edit a template",
+        &[],
+        &[Value::EntryTemplates],
+    ),
+    Command {
+        many: true,
+        ..command("today", "Add to today's running log", &[], &[Value::Text])
+    },
+    command("tui", "Browse entries in a full-screen interface", &[], &[]),
+    command(
+        "view",
+        "View an entry",
+        &[opt(
+            "raw",
+            None,
+            Value::Switch,
+            "print the content as written",
+        )],
+        &[Value::Ids],
+    ),
+];
+
+/// Returns the completion script for `shell`
+/// # Panics
+/// Will 'gracefully' exit if `shell` is not one of [`SHELLS`]
+pub fn script(shell: &str) -> String {
+    match shell {
+        "bash" => bash(),
+        "zsh" => zsh(),
+        "fish" => fish(),
+        _ => fail!("Unknown shell '{shell}', expected bash, zsh or fish"),
+    }
+}
+
+/// Prints what can be completed for `kind`, one per line, with the settings
+/// in `config`. Entry ids are followed by a tab and a description.
+/// # Panics
+/// Will 'gracefully' exit if `kind` is unknown
+pub fn print_candidates(kind: &str, config: &Config) {
+    match kind {
+        // Never ask for a passphrase in the middle of completing a command;
+        // without a cached key, describe entries by their dates only
+        "ids" if !crypto::unlock_from_cache() => {
+            let mut dates = get_entry_dates();
+            dates.sort_by_key(|&(id, date)| Reverse((date, id)));
+            for (id, date) in dates {
+                println!("{id}\t{}", date.display());
+            }
+        }
+        "ids" => {
+            let mut entries = get_entries();
+            entries.sort_by_key(|entry| Reverse((entry.date, entry.id)));
+            for entry in entries {
+                println!("{}\t{entry}", entry.id);
+            }
+        }
+        "tags" => get_tags().iter().for_each(|(tag, _)| println!("{tag}")),
+        "notebooks" => get_notebooks()
+            .iter()
+            .for_each(|(name, _)| println!("{name}")),
+        "settings" => config::KEYS.iter().for_each(|key| println!("{key}")),
+        "reports" => report::names().iter().for_each(|name| println!("{name}")),
+        "fields" => config.fields().iter().for_each(|name| println!("{name}")),
+        "templates" => templates::names()
+            .iter()
+            .for_each(|(name, _)| println!("{name}")),
+        _ => fail!("Unknown candidates '{kind}'"),
+    }
+}
+
+/// The name `--candidates` knows a dynamic value by
+fn candidates_kind(value: Value) -> Option<&'static str> {
+    match value {
+        Value::Ids => Some("ids"),
+        Value::Tags => Some("tags"),
+        Value::Notebooks => Some("notebooks"),
+        Value::Settings => Some("settings"),
+        Value::Templates => Some("reports"),
+        Value::EntryTemplates => Some("templates"),
+        Value::Fields => Some("fields"),
+        _ => None,
+    }
+}
+
+/// The names of the subcommands directly below `path`, with their help
+fn subcommands(path: &str) -> Vec<(&'static str, &'static str)> {
+    COMMANDS
+        .iter()
+        .filter_map(|command| {
+            let name = match path {
+                "" => command.path,
+                _ => command.path.strip_prefix(path)?.strip_prefix(' ')?,
+            };
+            match name.is_empty() || name.contains(' ') {
+                true => None,
+                false => Some((name, command.help)),
+            }
+        })
+        .collect()
+}
+
+/// The paths of every command below the top level
+fn subcommand_paths() -> impl Iterator<Item = &'static str> {
+    COMMANDS
+        .iter()
+        .map(|command| command.path)
+        .filter(|path| !path.is_empty())
+}
+
+/// The spellings of an option, e.g. `--title` and `-t`
+fn flags(opt: &Opt) -> Vec<String> {
+    let mut flags = vec![format!("--{}", opt.long)];
+    if let Some(short) = opt.short {
+        flags.push(format!("-{short}"));
+    }
+    flags
+}
+
+/// Every spelling of every option that takes a value
+fn value_flags() -> Vec<String> {
+    let mut flags: Vec<String> = COMMANDS
+        .iter()
+        .flat_map(|command| command.options)
+        .filter(|opt| opt.value != Value::Switch)
+        .flat_map(flags)
+        .collect();
+    flags.sort();
+    flags.dedup();
+    flags
+}
+
+/// For each positional argument of `command`, its index and whether the index
+/// is a minimum rather than exact
+fn positionals(command: &Command) -> impl Iterator<Item = (usize, bool, Value)> + '_ {
+    let last = command.positionals.len().saturating_sub(1);
+    command
+        .positionals
+        .iter()
+        .enumerate()
+        .map(move |(index, &value)| (index, command.many && index == last, value))
+}
+
+/// Quotes `text` for a POSIX-style shell
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+fn bash_values(value: Value) -> String {
+    let words = match (value, candidates_kind(value)) {
+        (Value::Files, _) => return String::from("COMPREPLY=($(compgen -f -- \"$cur\")); return"),
+        (Value::Directories, _) => {
+            return String::from("COMPREPLY=($(compgen -d -- \"$cur\")); return")
+        }
+        (Value::Ids, _) => String::from("$(_code_diary_candidates ids | cut -f1)"),
+        (_, Some(kind)) => format!("$(_code_diary_candidates {kind})"),
+        (Value::Choices(choices), _) => choices.join(" "),
+        _ => return String::from("return"),
+    };
+    format!("COMPREPLY=($(compgen -W \"{words}\" -- \"$cur\")); return")
+}
+
+fn bash() -> String {
+    let commands: Vec<&str> = subcommand_paths().collect();
+    let mut out = String::from(
+        "# bash completion for code_diary
+# Load with: source <(code_diary completions bash)
+
+_code_diary_candidates() {
+    code_diary ${notebook:+--notebook \"$notebook\"} completions --candidates \"$1\" 2>/dev/null
+}
+
+_code_diary() {
+    local cur=\"${COMP_WORDS[COMP_CWORD]}\" prev=\"${COMP_WORDS[COMP_CWORD-1]}\"
+    local cmd=\"\" notebook=\"\" word npos=0 i
+",
+    );
+    out.push_str(&format!(
+        "    local commands=\"|{}|\"\n    local takes_value=\" {} \"\n",
+        commands.join("|"),
+        value_flags().join(" ")
+    ));
+    out.push_str(
+        "    for ((i = 1; i < COMP_CWORD; i++)); do
+        word=\"${COMP_WORDS[i]}\"
+        if [[ \"$takes_value\" == *\" $word \"* ]]; then
+            i=$((i + 1))
+            if [[ \"$word\" == \"-n\" || \"$word\" == \"--notebook\" ]]; then
+                notebook=\"${COMP_WORDS[i]}\"
+            fi
+        elif [[ \"$word\" == -* ]]; then
+            continue
+        elif [[ $npos -eq 0 && \"$commands\" == *\"|${cmd:+$cmd }$word|\"* ]]; then
+            cmd=\"${cmd:+$cmd }$word\"
+        else
+            npos=$((npos + 1))
+        fi
+    done
+
+    COMPREPLY=()
+    case \"$cmd:$prev\" in
+",
+    );
+    for command in COMMANDS {
+        for opt in command.options {
+            if opt.value == Value::Switch {
+                continue;
+            }
+            let patterns: Vec<String> = flags(opt)
+                .iter()
+                .map(|flag| format!("\"{}:{flag}\"", command.path))
+                .collect();
+            out.push_str(&format!(
+                "        {}) {} ;;\n",
+                patterns.join("|"),
+                bash_values(opt.value)
+            ));
+        }
+    }
+    out.push_str(
+        "    esac
+
+    local subcommands=\"\" opts=\"--help\"
+    case \"$cmd\" in
+",
+    );
+    for command in COMMANDS {
+        let names: Vec<&str> = subcommands(command.path)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        let mut opts: Vec<String> = command.options.iter().flat_map(flags).collect();
+        opts.push(String::from("--help"));
+        out.push_str(&format!(
+            "        \"{}\") subcommands=\"{}\"; opts=\"{}\" ;;\n",
+            command.path,
+            names.join(" "),
+            opts.join(" ")
+        ));
+    }
+    out.push_str(
+        "    esac
+
+    if [[ \"$cur\" == -* ]]; then
+        COMPREPLY=($(compgen -W \"$opts\" -- \"$cur\"))
+        return
+    fi
+    if [[ $npos -eq 0 && -n \"$subcommands\" ]]; then
+        COMPREPLY=($(compgen -W \"$subcommands\" -- \"$cur\"))
+        return
+    fi
+
+    case \"$cmd:$npos\" in
+",
+    );
+    for command in COMMANDS {
+        for (index, many, value) in positionals(command) {
+            let pattern = match many {
+                true => format!("\"{}:\"*", command.path),
+                false => format!("\"{}:{index}\"", command.path),
+            };
+            out.push_str(&format!("        {pattern}) {} ;;\n", bash_values(value)));
+        }
+    }
+    out.push_str(
+        "    esac
+}
+
+complete -F _code_diary code_diary
+",
+    );
+    out
+}
+
+fn zsh_values(value: Value) -> String {
+    match (value, candidates_kind(value)) {
+        (Value::Ids, _) => String::from(
+            "local -a entries=(${(f)\"$(_code_diary_candidates ids)\"})
+            entries=(${entries//:/\\\\:})
+            entries=(${entries/$'\\t'/:})
+            _describe -t entries 'entry' entries; return",
+        ),
+        (_, Some(kind)) => {
+            format!("compadd -- ${{(f)\"$(_code_diary_candidates {kind})\"}}; return")
+        }
+        (Value::Choices(choices), _) => format!("compadd -- {}; return", choices.join(" ")),
+        (Value::Files, _) => String::from("_files; return"),
+        (Value::Directories, _) => String::from("_files -/; return"),
+        _ => String::from("return 1"),
+    }
+}
+
+fn zsh() -> String {
+    let commands: Vec<&str> = subcommand_paths().collect();
+    let mut out = String::from(
+        "#compdef code_diary
+# zsh completion for code_diary
+# Load with: source <(code_diary completions zsh)
+# or save as _code_diary somewhere in $fpath
+
+_code_diary_candidates() {
+    code_diary ${notebook:+--notebook \"$notebook\"} completions --candidates \"$1\" 2>/dev/null
+}
+
+_code_diary() {
+    local cur=\"${words[CURRENT]}\" prev=\"${words[CURRENT-1]}\"
+    local cmd=\"\" notebook=\"\" word npos=0 i
+",
+    );
+    out.push_str(&format!(
+        "    local commands=\"|{}|\"\n    local takes_value=\" {} \"\n",
+        commands.join("|"),
+        value_flags().join(" ")
+    ));
+    out.push_str(
+        "    for ((i = 2; i < CURRENT; i++)); do
+        word=\"${words[i]}\"
+        if [[ \"$takes_value\" == *\" $word \"* ]]; then
+            i=$((i + 1))
+            if [[ \"$word\" == \"-n\" || \"$word\" == \"--notebook\" ]]; then
+                notebook=\"${words[i]}\"
+            fi
+        elif [[ \"$word\" == -* ]]; then
+            continue
+        elif [[ $npos -eq 0 && \"$commands\" == *\"|${cmd:+$cmd }$word|\"* ]]; then
+            cmd=\"${cmd:+$cmd }$word\"
+        else
+            npos=$((npos + 1))
+        fi
+    done
+
+    case \"$cmd:$prev\" in
+",
+    );
+    for command in COMMANDS {
+        for opt in command.options {
+            if opt.value == Value::Switch {
+                continue;
+            }
+            let patterns: Vec<String> = flags(opt)
+                .iter()
+                .map(|flag| format!("\"{}:{flag}\"", command.path))
+                .collect();
+            out.push_str(&format!(
+                "        {})\n            {} ;;\n",
+                patterns.join("|"),
+                zsh_values(opt.value)
+            ));
+        }
+    }
+    out.push_str(
+        "    esac
+
+    local -a subcommands opts
+    case \"$cmd\" in
+",
+    );
+    for command in COMMANDS {
+        let names: Vec<String> = subcommands(command.path)
+            .into_iter()
+            .map(|(name, help)| quote(&format!("{name}:{help}")))
+            .collect();
+        let mut opts: Vec<String> = command
+            .options
+            .iter()
+            .flat_map(|opt| {
+                flags(opt)
+                    .into_iter()
+                    .map(|flag| quote(&format!("{flag}:{}", opt.help)))
+            })
+            .collect();
+        opts.push(quote("--help:display usage information"));
+        out.push_str(&format!(
+            "        \"{}\")\n            subcommands=({})\n            opts=({}) ;;\n",
+            command.path,
+            names.join(" "),
+            opts.join(" ")
+        ));
+    }
+    out.push_str(
+        "    esac
+
+    if [[ \"$cur\" == -* ]]; then
+        _describe -t options 'option' opts
+        return
+    fi
+    if [[ $npos -eq 0 && ${#subcommands} -gt 0 ]]; then
+        _describe -t commands 'command' subcommands
+        return
+    fi
+
+    case \"$cmd:$npos\" in
+",
+    );
+    for command in COMMANDS {
+        for (index, many, value) in positionals(command) {
+            let pattern = match many {
+                true => format!("\"{}:\"*", command.path),
+                false => format!("\"{}:{index}\"", command.path),
+            };
+            out.push_str(&format!(
+                "        {pattern})\n            {} ;;\n",
+                zsh_values(value)
+            ));
+        }
+    }
+    out.push_str(
+        "    esac
+    return 1
+}
+
+if [[ \"${funcstack[1]}\" == \"_code_diary\" ]]; then
+    _code_diary \"$@\"
+else
+    compdef _code_diary code_diary
+fi
+",
+    );
+    out
+}
+
+fn fish_values(value: Value) -> String {
+    match (value, candidates_kind(value)) {
+        (_, Some(kind)) => format!(" -a '(__code_diary_candidates {kind})'"),
+        (Value::Choices(choices), _) => format!(" -a '{}'", choices.join(" ")),
+        (Value::Files, _) => String::from(" -F"),
+        (Value::Directories, _) => String::from(" -a '(__fish_complete_directories)'"),
+        _ => String::new(),
+    }
+}
+
+fn fish() -> String {
+    let commands: Vec<String> = subcommand_paths().map(quote).collect();
+    let mut out = String::from(
+        "# fish completion for code_diary
+# Load with: code_diary completions fish | source
+",
+    );
+    out.push_str(&format!(
+        "\nset -g __code_diary_commands {}\nset -g __code_diary_takes_value {}\n",
+        commands.join(" "),
+        value_flags().join(" ")
+    ));
+    out.push_str(
+        "
+# Sets the subcommand typed so far, how many positional arguments follow it,
+# and the notebook chosen with --notebook
+function __code_diary_state
+    set -g __code_diary_cmd ''
+    set -g __code_diary_npos 0
+    set -g __code_diary_notebook ''
+    set -l skip ''
+    set -l tokens (commandline -opc)
+    set -e tokens[1]
+    for word in $tokens
+        if test -n \"$skip\"
+            test \"$skip\" = notebook; and set __code_diary_notebook $word
+            set skip ''
+        else if contains -- $word $__code_diary_takes_value
+            set skip value
+            contains -- $word -n --notebook; and set skip notebook
+        else if string match -q -- '-*' $word
+            continue
+        else if test $__code_diary_npos -eq 0; and contains -- (string trim -- \"$__code_diary_cmd $word\") $__code_diary_commands
+            set __code_diary_cmd (string trim -- \"$__code_diary_cmd $word\")
+        else
+            set __code_diary_npos (math $__code_diary_npos + 1)
+        end
+    end
+end
+
+# Succeeds if the subcommand typed so far is $cmd and, if given, the next
+# positional argument is number $npos (or at least $npos, with a trailing +)
+function __code_diary_at -a cmd npos
+    __code_diary_state
+    test \"$__code_diary_cmd\" = \"$cmd\"; or return 1
+    test -z \"$npos\"; and return 0
+    if string match -q -- '*+' $npos
+        test $__code_diary_npos -ge (string trim -c + -- $npos)
+    else
+        test $__code_diary_npos -eq $npos
+    end
+end
+
+function __code_diary_candidates -a kind
+    __code_diary_state
+    if test -n \"$__code_diary_notebook\"
+        code_diary --notebook $__code_diary_notebook completions --candidates $kind 2>/dev/null
+    else
+        code_diary completions --candidates $kind 2>/dev/null
+    end
+end
+
+complete -c code_diary -f
+",
+    );
+    for command in COMMANDS {
+        let mut lines = String::new();
+        let at = format!("__code_diary_at {}", quote(command.path));
+        for (name, help) in subcommands(command.path) {
+            lines.push_str(&format!(
+                "complete -c code_diary -n \"{at} 0\" -a {name} -d {}\n",
+                quote(help)
+            ));
+        }
+        for opt in command.options {
+            let short = match opt.short {
+                Some(short) => format!(" -s {short}"),
+                None => String::new(),
+            };
+            let value = match opt.value {
+                Value::Switch => String::new(),
+                value => format!(" -x{}", fish_values(value)),
+            };
+            lines.push_str(&format!(
+                "complete -c code_diary -n \"{at}\" -l {}{short}{value} -d {}\n",
+                opt.long,
+                quote(opt.help)
+            ));
+        }
+        for (index, many, value) in positionals(command) {
+            let values = fish_values(value);
+            if values.is_empty() {
+                continue;
+            }
+            let plus = if many { "+" } else { "" };
+            lines.push_str(&format!(
+                "complete -c code_diary -n \"{at} {index}{plus}\"{values}\n"
+            ));
+        }
+        if !lines.is_empty() {
+            out.push('\n');
+            out.push_str(&lines);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use argh::FromArgs;
+
+    use super::*;
+
+    /// Returns the subcommands and long options that argh lists in the help
+    /// for the command at `path`
+    fn help(path: &str) -> (Vec<String>, Vec<String>) {
+        let mut args = vec!["code_diary"];
+        args.extend(path.split_whitespace());
+        args.push("--help");
+        let output = match crate::Cli::from_args(&args[..1], &args[1..]) {
+            Err(exit) => exit.output,
+            Ok(_) => panic!("`{path} --help` did not print help"),
+        };
+
+        let mut subcommands = Vec::new();
+        let mut options = Vec::new();
+        let mut section = "";
+        for line in output.lines() {
+            if !line.starts_with(' ') {
+                section = line;
+                continue;
+            }
+            // Descriptions carry on further in
+            let Some(line) = line
+                .strip_prefix("  ")
+                .filter(|line| !line.starts_with(' '))
+            else {
+                continue;
+            };
+            match section {
+                "Commands:" => subcommands.extend(line.split_whitespace().next().map(String::from)),
+                "Options:" => options.extend(
+                    line.split_whitespace()
+                        .find_map(|word| word.strip_prefix("--"))
+                        .map(|long| long.trim_end_matches(','))
+                        .filter(|&long| long != "help")
+                        .map(String::from),
+                ),
+                _ => (),
+            }
+        }
+        (subcommands, options)
+    }
+
+    #[test]
+    fn commands_match_every_subcommand_and_option() {
+        let mut seen = Vec::new();
+        let mut paths = vec![String::new()];
+        while let Some(path) = paths.pop() {
+            let Some(command) = COMMANDS.iter().find(|command| command.path == path) else {
+                panic!("COMMANDS is missing `{path}`");
+            };
+            let (subcommands, options) = help(&path);
+            for option in &options {
+                assert!(
+                    command.options.iter().any(|known| known.long == option),
+                    "COMMANDS is missing `{path} --{option}`"
+                );
+            }
+            for known in command.options {
+                assert!(
+                    options.iter().any(|option| option == known.long),
+                    "COMMANDS has `{path} --{}`, which `Cli` does not",
+                    known.long
+                );
+            }
+            paths.extend(
+                subcommands
+                    .iter()
+                    .map(|subcommand| format!("{path} {subcommand}").trim().to_string()),
+            );
+            seen.push(path);
+        }
+        for command in COMMANDS {
+            assert!(
+                seen.iter().any(|path| path == command.path),
+                "COMMANDS has `{}`, which `Cli` does not",
+                command.path
+            );
+        }
+    }
+}
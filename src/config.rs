@@ -0,0 +1,282 @@
+//! Loads and saves the user's configuration, stored as TOML at
+//! `$XDG_CONFIG_HOME/code_diary/config.toml` (or `~/.config/code_diary/config.toml`).
+//!
+//! Every setting is optional; the accessors on [`Config`] fill in the defaults.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::fail;
+
+/// The number of entries `list` shows when no limit is configured
+pub const DEFAULT_LIST_LIMIT: u64 = 20;
+
+/// How many backups are kept when no number is configured
+pub const DEFAULT_BACKUP_KEEP: usize = 10;
+
+/// How many automatic backups are kept when no number is configured
+pub const DEFAULT_AUTO_BACKUP_KEEP: usize = 10;
+
+/// The largest file, in megabytes, `add --attach` stores when no limit is
+/// configured
+pub const DEFAULT_MAX_ATTACHMENT_MB: u64 = 10;
+
+/// The names of every setting, in the order `config list` prints them
+pub const KEYS: &[&str] = &[
+    "db_path",
+    "default_notebook",
+    "editor",
+    "date_format",
+    "theme",
+    "list_limit",
+    "prompt_title",
+    "max_attachment_mb",
+    "fields",
+    "backup_dir",
+    "backup_keep",
+    "auto_backup_keep",
+];
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+/// The contents of the configuration file
+pub struct Config {
+    /// Where the diary database lives
+    pub db_path: Option<PathBuf>,
+    /// The notebook used when `--notebook` is not given
+    pub default_notebook: Option<String>,
+    /// The editor used to write entries. When unset, entries are typed into a
+    /// prompt.
+    pub editor: Option<String>,
+    /// A [`chrono` format string](https://docs.rs/chrono/latest/chrono/format/strftime/index.html)
+    /// for displaying dates
+    pub date_format: Option<String>,
+    /// The color theme: `dark`, `light` or `mono`
+    pub theme: Option<String>,
+    /// How many entries `list` shows by default
+    pub list_limit: Option<u64>,
+    /// Whether `add` asks for a title when none is given
+    pub prompt_title: Option<bool>,
+    /// The largest file `add --attach` stores, in megabytes
+    pub max_attachment_mb: Option<u64>,
+    /// The ratings `add` asks for, e.g. `mood` and `energy`
+    pub fields: Option<Vec<String>>,
+    /// Where `backup` puts copies of the database
+    pub backup_dir: Option<PathBuf>,
+    /// How many backups are kept before the oldest are removed
+    pub backup_keep: Option<usize>,
+    /// How many automatic backups, taken before destructive commands, are kept
+    /// before the oldest are removed
+    pub auto_backup_keep: Option<usize>,
+}
+
+impl Config {
+    /// Reads the configuration file, falling back to the defaults if there is
+    /// none.
+    /// # Panics
+    /// Will 'gracefully' exit if the file exists but cannot be parsed
+    pub fn load() -> Self {
+        let path = config_path();
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                let path = path.display();
+                fail!("Could not read {path}: {e}")
+            }
+        };
+        match toml::from_str(&text) {
+            Ok(config) => config,
+            Err(e) => {
+                let path = path.display();
+                fail!("Could not parse {path}: {e}")
+            }
+        }
+    }
+
+    /// Writes the configuration file, creating its directory if needed
+    pub fn save(&self) {
+        let dir = config_dir();
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            let dir = dir.display();
+            fail!("Could not create {dir}: {e}");
+        }
+        let path = config_path();
+        let text = toml::to_string(self).unwrap();
+        if let Err(e) = std::fs::write(&path, text) {
+            let path = path.display();
+            fail!("Could not write {path}: {e}");
+        }
+    }
+
+    /// Returns the database path, defaulting to `~/code_diary.db`
+    pub fn db_path(&self) -> PathBuf {
+        match &self.db_path {
+            Some(path) => path.clone(),
+            None => PathBuf::from(home()).join("code_diary.db"),
+        }
+    }
+
+    /// Returns where backups go, defaulting to `code_diary_backups` next to
+    /// the database
+    pub fn backup_dir(&self) -> PathBuf {
+        match &self.backup_dir {
+            Some(dir) => dir.clone(),
+            None => {
+                let db_path = self.db_path();
+                let parent = db_path.parent().unwrap_or(std::path::Path::new("."));
+                parent.join("code_diary_backups")
+            }
+        }
+    }
+
+    /// Returns how many backups are kept
+    pub fn backup_keep(&self) -> usize {
+        self.backup_keep.unwrap_or(DEFAULT_BACKUP_KEEP)
+    }
+
+    /// Returns how many automatic backups are kept
+    pub fn auto_backup_keep(&self) -> usize {
+        self.auto_backup_keep.unwrap_or(DEFAULT_AUTO_BACKUP_KEEP)
+    }
+
+    /// Returns the editor for commands that cannot fall back to a prompt:
+    /// the configured one, then `$VISUAL`, then `$EDITOR`, then `vi`.
+    pub fn editor_or_default(&self) -> String {
+        self.editor
+            .clone()
+            .or_else(|| std::env::var("VISUAL").ok())
+            .or_else(|| std::env::var("EDITOR").ok())
+            .filter(|editor| !editor.trim().is_empty())
+            .unwrap_or_else(|| String::from("vi"))
+    }
+
+    /// Returns the color theme, defaulting to `dark`
+    pub fn theme(&self) -> String {
+        self.theme.clone().unwrap_or_else(|| String::from("dark"))
+    }
+
+    /// Returns how many entries `list` shows by default
+    pub fn list_limit(&self) -> u64 {
+        self.list_limit.unwrap_or(DEFAULT_LIST_LIMIT)
+    }
+
+    /// Returns the largest file `add --attach` stores, in megabytes
+    pub fn max_attachment_mb(&self) -> u64 {
+        self.max_attachment_mb.unwrap_or(DEFAULT_MAX_ATTACHMENT_MB)
+    }
+
+    /// Returns the ratings `add` asks for, defaulting to none
+    pub fn fields(&self) -> Vec<String> {
+        self.fields.clone().unwrap_or_default()
+    }
+
+    /// Returns whether `add` asks for a title, defaulting to `true`
+    pub fn prompt_title(&self) -> bool {
+        self.prompt_title.unwrap_or(true)
+    }
+
+    /// Returns the effective value of a setting as a string, or `None` if the
+    /// setting is unset and has no default.
+    /// # Panics
+    /// Will 'gracefully' exit if `key` is not a setting
+    pub fn get(&self, key: &str) -> Option<String> {
+        match key {
+            "db_path" => Some(self.db_path().display().to_string()),
+            "default_notebook" => self.default_notebook.clone(),
+            "editor" => self.editor.clone(),
+            "date_format" => self.date_format.clone(),
+            "theme" => Some(self.theme()),
+            "list_limit" => Some(self.list_limit().to_string()),
+            "prompt_title" => Some(self.prompt_title().to_string()),
+            "max_attachment_mb" => Some(self.max_attachment_mb().to_string()),
+            "fields" => self.fields.as_ref().map(|fields| fields.join(",")),
+            "backup_dir" => Some(self.backup_dir().display().to_string()),
+            "backup_keep" => Some(self.backup_keep().to_string()),
+            "auto_backup_keep" => Some(self.auto_backup_keep().to_string()),
+            _ => fail!("Unknown setting '{key}'"),
+        }
+    }
+
+    /// Sets a setting from its string representation
+    /// # Panics
+    /// Will 'gracefully' exit if `key` is not a setting or `value` is invalid
+    /// for it
+    pub fn set(&mut self, key: &str, value: &str) {
+        match key {
+            "db_path" => self.db_path = Some(absolute(key, value)),
+            "default_notebook" => self.default_notebook = Some(value.to_string()),
+            "editor" => self.editor = Some(value.to_string()),
+            "date_format" => self.date_format = Some(value.to_string()),
+            "theme" => match crate::theme::THEMES.contains(&value) {
+                true => self.theme = Some(value.to_string()),
+                false => fail!("theme must be one of dark, light or mono"),
+            },
+            "list_limit" => match value.parse() {
+                Ok(limit) => self.list_limit = Some(limit),
+                Err(_) => fail!("list_limit must be a number"),
+            },
+            "prompt_title" => match value.parse() {
+                Ok(prompt) => self.prompt_title = Some(prompt),
+                Err(_) => fail!("prompt_title must be true or false"),
+            },
+            "max_attachment_mb" => match value.parse() {
+                Ok(limit) => self.max_attachment_mb = Some(limit),
+                Err(_) => fail!("max_attachment_mb must be a number"),
+            },
+            "fields" => {
+                let fields: Vec<String> = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(String::from)
+                    .collect();
+                fields
+                    .iter()
+                    .for_each(|name| crate::fields::check_name(name));
+                self.fields = Some(fields);
+            }
+            "backup_dir" => self.backup_dir = Some(absolute(key, value)),
+            "backup_keep" => match value.parse() {
+                Ok(keep) if keep > 0 => self.backup_keep = Some(keep),
+                _ => fail!("backup_keep must be a number above 0"),
+            },
+            "auto_backup_keep" => match value.parse() {
+                Ok(keep) if keep > 0 => self.auto_backup_keep = Some(keep),
+                _ => fail!("auto_backup_keep must be a number above 0"),
+            },
+            _ => fail!("Unknown setting '{key}'"),
+        }
+    }
+}
+
+/// Returns the directory holding `config.toml` and other user files
+pub fn config_dir() -> PathBuf {
+    let base = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(home()).join(".config"),
+    };
+    base.join("code_diary")
+}
+
+/// Returns the path of the configuration file
+pub fn config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+/// Resolves the path `value` given for the setting `key` against the current
+/// directory, so the setting means the same wherever the diary is used from
+fn absolute(key: &str, value: &str) -> PathBuf {
+    match std::path::absolute(value) {
+        Ok(path) => path,
+        Err(e) => fail!("{key} must be a path: {e}"),
+    }
+}
+
+fn home() -> String {
+    match std::env::var("HOME") {
+        Ok(home) => home,
+        Err(_) => fail!("$HOME is not set!"),
+    }
+}
@@ -0,0 +1,413 @@
+//! Optional at-rest encryption of entry titles, contents and attachments.
+//!
+//! When a diary is initialized with `code_diary init --encrypt`, titles,
+//! contents and the names and contents of attachments are sealed with
+//! XChaCha20-Poly1305 under a key derived from a passphrase with Argon2.
+//! Attachments are identified by a BLAKE2 hash keyed with a subkey of it, so
+//! the hash does not tell whether a guessed file is attached. Everything else
+//! stays in the clear so the database remains readable by `sqlite3`: ids,
+//! timestamps, notebooks, tags, repository names, file references, rating
+//! fields, links, tracked time, imported commit hashes and attachment sizes.
+//! Entries are decrypted as they are loaded by
+//! [`get_entries`](crate::backend::get_entries), so listing and searching work
+//! on plaintext once the diary has been unlocked.
+
+use std::sync::OnceLock;
+
+use argon2::Argon2;
+use blake2::digest::Mac;
+use blake2::{Blake2s256, Blake2sMac256, Digest};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+use crate::backend::{encrypt_diary, get_setting};
+use crate::fail;
+
+/// The settings key marking a diary as encrypted
+const ENCRYPTED: &str = "Encrypted";
+/// The settings key holding the hex encoded Argon2 salt
+const SALT: &str = "Salt";
+/// The settings key holding a sealed known value used to check passphrases
+const VERIFIER: &str = "Verifier";
+/// The settings key recording whether the derived key may be cached
+const KEY_CACHE: &str = "KeyCache";
+
+/// The plaintext sealed into [`VERIFIER`]
+const VERIFIER_TEXT: &str = "code_diary";
+/// Length of the XChaCha20 nonce prepended to every sealed value
+const NONCE_LEN: usize = 24;
+/// How many times the user may retry a wrong passphrase
+const ATTEMPTS: usize = 3;
+/// The BLAKE2 personalization the attachment hash key is derived with
+const HASH_KEY_PERSONAL: &[u8] = b"cd-blobs";
+
+static CIPHER: OnceLock<XChaCha20Poly1305> = OnceLock::new();
+/// The key attachments are hashed with, set along with [`CIPHER`]
+static HASH_KEY: OnceLock<[u8; 32]> = OnceLock::new();
+
+/// Returns whether the diary has been initialized with encryption
+pub fn is_enabled() -> bool {
+    get_setting(ENCRYPTED).as_deref() == Some("1")
+}
+
+/// Returns the cipher for the diary, unlocking it on first use. Returns `None`
+/// if the diary is not encrypted.
+/// # Panics
+/// Will 'gracefully' exit if the passphrase is wrong too many times
+pub fn cipher() -> Option<&'static XChaCha20Poly1305> {
+    if let Some(cipher) = CIPHER.get() {
+        return Some(cipher);
+    }
+    if !is_enabled() {
+        return None;
+    }
+    Some(CIPHER.get_or_init(unlock))
+}
+
+/// Seals `text` for storage. Without a cipher, `text` is returned unchanged.
+pub fn seal(cipher: Option<&XChaCha20Poly1305>, text: &str) -> String {
+    match cipher {
+        Some(_) => to_hex(&seal_bytes(cipher, text.as_bytes())),
+        None => text.to_string(),
+    }
+}
+
+/// Returns the hex BLAKE2 hash that identifies the attached file `data`. In an
+/// encrypted diary it is keyed, unlocking the diary on first use.
+pub fn hash_blob(data: &[u8]) -> String {
+    match cipher().and(HASH_KEY.get()) {
+        Some(key) => keyed_hash(key, data),
+        None => to_hex(&Blake2s256::digest(data)),
+    }
+}
+
+/// Returns the hex BLAKE2 hash of `data` keyed with `key`, the attachment hash
+/// key of an encrypted diary
+pub fn keyed_hash(key: &[u8; 32], data: &[u8]) -> String {
+    let mut mac = match <Blake2sMac256 as Mac>::new_from_slice(key) {
+        Ok(mac) => mac,
+        Err(e) => fail!("Encryption Error: {e}"),
+    };
+    mac.update(data);
+    to_hex(&mac.finalize().into_bytes())
+}
+
+/// Same as [`seal`] but for binary data like attachments, which is stored as
+/// bytes rather than hex
+pub fn seal_bytes(cipher: Option<&XChaCha20Poly1305>, data: &[u8]) -> Vec<u8> {
+    let cipher = match cipher {
+        Some(cipher) => cipher,
+        None => return data.to_vec(),
+    };
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let sealed = match cipher.encrypt(&nonce, data) {
+        Ok(sealed) => sealed,
+        Err(_) => fail!("Encryption Error: Could not seal entry!"),
+    };
+    let mut bytes = nonce.to_vec();
+    bytes.extend(sealed);
+    bytes
+}
+
+/// Opens data produced by [`seal_bytes`]
+/// # Panics
+/// Will 'gracefully' exit if the data has been tampered with
+pub fn open_bytes(cipher: Option<&XChaCha20Poly1305>, data: &[u8]) -> Vec<u8> {
+    let cipher = match cipher {
+        Some(cipher) => cipher,
+        None => return data.to_vec(),
+    };
+    match try_open_bytes(cipher, data) {
+        Some(data) => data,
+        None => fail!("Encryption Error: An attachment could not be decrypted!"),
+    }
+}
+
+/// Opens a value produced by [`seal`]. Without a cipher, `text` is returned
+/// unchanged.
+/// # Panics
+/// Will 'gracefully' exit if the value has been tampered with
+pub fn open(cipher: Option<&XChaCha20Poly1305>, text: &str) -> String {
+    let cipher = match cipher {
+        Some(cipher) => cipher,
+        None => return text.to_string(),
+    };
+    match try_open(cipher, text) {
+        Some(text) => text,
+        None => fail!("Encryption Error: An entry could not be decrypted!"),
+    }
+}
+
+/// Turns on encryption for the diary, prompting for a new passphrase and
+/// sealing every entry. If `cache_key` is set, the derived key is kept in the
+/// session keyring so later commands do not prompt again. Returns how many
+/// entries were sealed.
+pub fn enable(cache_key: bool) -> u64 {
+    if is_enabled() {
+        fail!("The diary is already encrypted!");
+    }
+
+    let passphrase = prompt_passphrase("Choose a passphrase");
+    let confirmation = prompt_passphrase("Repeat the passphrase");
+    if passphrase != confirmation {
+        fail!("Passphrases do not match!");
+    }
+    if passphrase.is_empty() {
+        fail!("The passphrase cannot be empty!");
+    }
+
+    let salt: [u8; 16] = rand_bytes();
+    let key = derive_key(&passphrase, &salt);
+    let cipher = XChaCha20Poly1305::new(&key.into());
+
+    let salt = to_hex(&salt);
+    let verifier = seal(Some(&cipher), VERIFIER_TEXT);
+    let settings = [
+        (SALT, salt.as_str()),
+        (VERIFIER, verifier.as_str()),
+        (KEY_CACHE, if cache_key { "1" } else { "0" }),
+        (ENCRYPTED, "1"),
+    ];
+    let hash_key = derive_hash_key(&key);
+    let sealed = encrypt_diary(&cipher, &hash_key, &settings);
+
+    if cache_key {
+        keyring::store(&salt, &key);
+    }
+
+    let _ = CIPHER.set(cipher);
+    let _ = HASH_KEY.set(hash_key);
+    sealed
+}
+
+/// Unlocks the diary with the key in the session keyring, without ever asking
+/// for the passphrase. Returns whether the diary can be read, which it always
+/// can if it is not encrypted.
+pub fn unlock_from_cache() -> bool {
+    if CIPHER.get().is_some() || !is_enabled() {
+        return true;
+    }
+    let salt = get_setting(SALT);
+    let verifier = get_setting(VERIFIER);
+    match salt.zip(verifier) {
+        Some((salt, verifier)) => match cached(&salt, &verifier) {
+            Some(cipher) => {
+                let _ = CIPHER.set(cipher);
+                true
+            }
+            None => false,
+        },
+        None => false,
+    }
+}
+
+/// Returns the cipher for the key in the session keyring, if the user asked
+/// for it to be cached and it is still there
+fn cached(salt_hex: &str, verifier: &str) -> Option<XChaCha20Poly1305> {
+    if get_setting(KEY_CACHE).as_deref() != Some("1") {
+        return None;
+    }
+    let key = keyring::load(salt_hex)?;
+    let cipher = XChaCha20Poly1305::new(&key.into());
+    match try_open(&cipher, verifier).as_deref() == Some(VERIFIER_TEXT) {
+        true => {
+            let _ = HASH_KEY.set(derive_hash_key(&key));
+            Some(cipher)
+        }
+        false => None,
+    }
+}
+
+fn unlock() -> XChaCha20Poly1305 {
+    let salt = match get_setting(SALT).as_deref().and_then(from_hex) {
+        Some(salt) => salt,
+        None => fail!("Encryption Error: The diary has no salt!"),
+    };
+    let verifier = match get_setting(VERIFIER) {
+        Some(verifier) => verifier,
+        None => fail!("Encryption Error: The diary has no verifier!"),
+    };
+    let cache_key = get_setting(KEY_CACHE).as_deref() == Some("1");
+    let salt_hex = to_hex(&salt);
+
+    if let Some(cipher) = cached(&salt_hex, &verifier) {
+        return cipher;
+    }
+
+    for _ in 0..ATTEMPTS {
+        let passphrase = prompt_passphrase("Passphrase");
+        let key = derive_key(&passphrase, &salt);
+        let cipher = XChaCha20Poly1305::new(&key.into());
+        if try_open(&cipher, &verifier).as_deref() == Some(VERIFIER_TEXT) {
+            if cache_key {
+                keyring::store(&salt_hex, &key);
+            }
+            let _ = HASH_KEY.set(derive_hash_key(&key));
+            return cipher;
+        }
+        eprintln!("Wrong passphrase!");
+    }
+
+    fail!("Could not unlock the diary!");
+}
+
+fn try_open(cipher: &XChaCha20Poly1305, text: &str) -> Option<String> {
+    String::from_utf8(try_open_bytes(cipher, &from_hex(text)?)?).ok()
+}
+
+fn try_open_bytes(cipher: &XChaCha20Poly1305, data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, sealed) = data.split_at(NONCE_LEN);
+    cipher.decrypt(XNonce::from_slice(nonce), sealed).ok()
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    if let Err(e) = Argon2::default().hash_password_into(passphrase.as_bytes(), salt, &mut key) {
+        fail!("Encryption Error: {e}");
+    }
+    key
+}
+
+/// Derives the key attachments are hashed with from the diary's `key`, so
+/// the hashes reveal nothing about the key that seals them
+fn derive_hash_key(key: &[u8; 32]) -> [u8; 32] {
+    let mac = match Blake2sMac256::new_with_salt_and_personal(key, &[], HASH_KEY_PERSONAL) {
+        Ok(mac) => mac,
+        Err(e) => fail!("Encryption Error: {e}"),
+    };
+    mac.finalize().into_bytes().into()
+}
+
+fn prompt_passphrase(message: &str) -> String {
+    let question = requestty::Question::password("passphrase")
+        .message(message)
+        .mask('*')
+        .build();
+    match requestty::prompt_one(question) {
+        Ok(answer) => answer.as_string().unwrap_or_default().to_string(),
+        Err(_) => fail!("Failed to take input!"),
+    }
+}
+
+fn rand_bytes<const N: usize>() -> [u8; N] {
+    use chacha20poly1305::aead::rand_core::RngCore;
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+mod keyring {
+    //! Caches the derived key in the kernel session keyring
+
+    use linux_keyutils::{KeyRing, KeyRingIdentifier};
+
+    /// How long a cached key stays valid, in seconds
+    const TIMEOUT: usize = 60 * 60;
+
+    fn description(salt: &str) -> String {
+        format!("code_diary:{salt}")
+    }
+
+    pub fn load(salt: &str) -> Option<[u8; 32]> {
+        let ring = KeyRing::from_special_id(KeyRingIdentifier::Session, false).ok()?;
+        let key = ring.search(&description(salt)).ok()?;
+        key.read_to_vec().ok()?.try_into().ok()
+    }
+
+    pub fn store(salt: &str, secret: &[u8; 32]) {
+        let ring = match KeyRing::from_special_id(KeyRingIdentifier::Session, true) {
+            Ok(ring) => ring,
+            Err(_) => return,
+        };
+        if let Ok(key) = ring.add_key(&description(salt), secret) {
+            let _ = key.set_timeout(TIMEOUT);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod keyring {
+    //! Key caching is only supported through the Linux kernel keyring
+
+    pub fn load(_salt: &str) -> Option<[u8; 32]> {
+        None
+    }
+
+    pub fn store(_salt: &str, _secret: &[u8; 32]) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher(key: u8) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(&[key; 32].into())
+    }
+
+    #[test]
+    fn sealed_text_opens_with_the_same_key() {
+        let text = "Dear diary,\nthe ünïcode survived";
+        let sealed = seal(Some(&cipher(1)), text);
+        assert!(!sealed.contains("diary"));
+        assert_eq!(open(Some(&cipher(1)), &sealed), text);
+        // Every value gets its own nonce
+        assert_ne!(seal(Some(&cipher(1)), text), sealed);
+    }
+
+    #[test]
+    fn the_wrong_key_cannot_open() {
+        let sealed = seal(Some(&cipher(1)), "secret");
+        assert_eq!(try_open(&cipher(2), &sealed), None);
+    }
+
+    #[test]
+    fn tampered_values_are_rejected() {
+        let sealed = seal(Some(&cipher(1)), "secret");
+        let bytes = from_hex(&sealed).unwrap();
+        for index in [0, NONCE_LEN, bytes.len() - 1] {
+            let mut tampered = bytes.clone();
+            tampered[index] ^= 1;
+            assert_eq!(try_open(&cipher(1), &to_hex(&tampered)), None);
+        }
+        assert_eq!(try_open(&cipher(1), &sealed[..sealed.len() - 2]), None);
+        assert_eq!(try_open(&cipher(1), &sealed[..NONCE_LEN]), None);
+        assert_eq!(try_open(&cipher(1), "not hex"), None);
+    }
+
+    #[test]
+    fn without_a_cipher_text_is_kept_as_is() {
+        assert_eq!(seal(None, "plain"), "plain");
+        assert_eq!(open(None, "plain"), "plain");
+    }
+
+    #[test]
+    fn attachment_hashes_depend_on_the_key() {
+        let plain = to_hex(&Blake2s256::digest(b"log"));
+        let hashes: Vec<String> = [[1u8; 32], [2u8; 32]]
+            .iter()
+            .map(|key| keyed_hash(&derive_hash_key(key), b"log"))
+            .collect();
+        assert_eq!(hashes[0], keyed_hash(&derive_hash_key(&[1u8; 32]), b"log"));
+        assert_ne!(hashes[0], hashes[1]);
+        assert!(!hashes.contains(&plain));
+        assert_ne!(derive_hash_key(&[1u8; 32]), [1u8; 32]);
+    }
+}
@@ -1,20 +1,16 @@
 //! A module containing the `DiaryEntry` struct and related implementations
 
-use crate::backend::get_latest_id;
+use crate::attachments::{show_size, Attachment};
+use crate::backend::{current_notebook, get_latest_id};
+use crate::git;
+use crate::refs::FileRef;
+use crate::sessions::show_duration;
+use crate::theme::{paint, Role};
 use crate::timestamps::TimeStamp;
 
-#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Clone, Default, Ord, PartialOrd, Eq, PartialEq)]
 /// An internal struct consisting of a timestamp, title, body, and a boolean
 /// determining whether title has been manually added by the user
-/// ## Example:
-/// ```rust
-/// use diaryentry::DiaryEntry;
-///
-/// fn main {
-///     // Prompts a user for the entry and stores it.
-///     let entry = DiaryEntry::from_prompt();
-/// }
-/// ```
 pub struct DiaryEntry {
     /// The program-wide unique id for this diary entry
     pub id: u64,
@@ -24,6 +20,26 @@ pub struct DiaryEntry {
     pub title: Option<String>,
     /// The content of the diary entry
     pub content: String,
+    /// The name of the notebook the entry belongs to
+    pub notebook: String,
+    /// Free-form labels, e.g. `bug` or `til`
+    pub tags: Vec<String>,
+    /// The name of the git repository the entry was written in, if any
+    pub repo: Option<String>,
+    /// The places in the code the entry is about
+    pub refs: Vec<FileRef>,
+    /// The files kept with the entry
+    pub attachments: Vec<Attachment>,
+    /// When the work the entry records ended, for entries made by `stop`.
+    /// The work started at `date`.
+    pub ended: Option<TimeStamp>,
+    /// Ratings like mood or energy, from 1 to 5, in the order of the
+    /// `fields` setting
+    pub fields: Vec<(String, u8)>,
+    /// Whether the entry is kept at the top of `list` and the entry picker
+    pub pinned: bool,
+    /// Whether the entry is marked as important, for `list --starred`
+    pub starred: bool,
 }
 
 impl DiaryEntry {
@@ -36,6 +52,15 @@ impl DiaryEntry {
             title: title.to_owned(),
             content: content.to_string(),
             date,
+            notebook: current_notebook(),
+            tags: Vec::new(),
+            repo: git::repo_name(),
+            refs: Vec::new(),
+            attachments: Vec::new(),
+            ended: None,
+            fields: Vec::new(),
+            pinned: false,
+            starred: false,
         }
     }
     /// Takes a title and body and generates a timestamp, returning a `DiaryEntry`
@@ -60,70 +85,153 @@ impl DiaryEntry {
             date: TimeStamp::new(),
             title: Some(title.to_string()),
             content: content.to_string(),
+            notebook: current_notebook(),
+            tags: Vec::new(),
+            repo: git::repo_name(),
+            refs: Vec::new(),
+            attachments: Vec::new(),
+            ended: None,
+            fields: Vec::new(),
+            pinned: false,
+            starred: false,
         }
     }
-    /// Prompts the user for a entry and returns it.
-    ///
-    /// ## Example
-    /// ```rust
-    /// use diaryentry::DiaryEntry;
-    ///
-    /// fn main() {
-    ///     let input_entry = DiaryEntry::from_prompt();
-    ///     match input_entry {
-    ///         DiaryEntry { TimeStamp, Option<String>, String } => println!("yay!"),
-    ///         _ => println!("uh oh!")
-    ///     }
-    /// }
-    /// ```
-    pub fn from_prompt() -> Self {
-        let id = get_latest_id() + 1;
-        let date = TimeStamp::new();
-        let title_question = requestty::Question::input("Title")
-            .default("")
-            .message("Please give a title (Optional))")
-            .build();
-        let content = requestty::Question::input("Content")
-            .message("Please enter your diary entry")
-            .build();
-
-        let title_answer = requestty::prompt_one(title_question).unwrap();
-        let title_answer = title_answer.as_string().unwrap();
-
-        let title = match title_answer {
-            "" => None,
-            _ => Some(title_answer.to_string()),
+    /// Adds `text` to the end of the content as a list item stamped with the
+    /// current time, e.g. `- 14:05 fixed the flaky test`. The change still has
+    /// to be saved with [`update_entry`](crate::backend::update_entry).
+    pub fn append_line(&mut self, text: &str) {
+        let now = chrono::Local::now().format("%H:%M");
+        let line = format!("- {now} {}", text.trim());
+        self.content = match self.content.trim_end() {
+            "" => line,
+            content => format!("{content}\n{line}"),
         };
+    }
 
-        let content = requestty::prompt_one(content);
-
-        if content.is_err() {
-            eprintln!("Failed to take input!");
-            std::process::exit(1);
-        }
-        if content.as_ref().unwrap().as_string().is_none() {
-            eprintln!("Answer could not be taken!");
-            std::process::exit(1);
-        }
-        let content = &content.unwrap().as_string().unwrap().to_string();
-        Self {
-            id,
-            date,
-            title,
-            content: content.clone(),
-        }
+    /// How long the work the entry records took, for entries made by `stop`
+    pub fn duration(&self) -> Option<chrono::Duration> {
+        let ended = self.ended?.to_datetime()?;
+        Some(ended - self.date.to_datetime()?)
     }
+
     /// Displays (converts to string) a diary entry in detailed, colorful format
     pub fn show_detail(&self) -> String {
+        format!(
+            "{}:\n{}",
+            self.show_header(),
+            paint(Role::Body, &self.content)
+        )
+    }
+
+    /// Displays a diary entry on one line with its id, as in `list`, marked
+    /// if it is pinned or starred
+    pub fn show_summary(&self) -> String {
+        format!(
+            "{} {}{}",
+            paint(Role::Muted, &format!("{:>4}", self.id)),
+            self.show_header(),
+            paint(Role::Accent, &self.marks())
+        )
+    }
+
+    /// ` ⚑` if the entry is pinned and ` ★` if it is starred
+    pub fn marks(&self) -> String {
+        let pin = if self.pinned { " ⚑" } else { "" };
+        let star = if self.starred { " ★" } else { "" };
+        format!("{pin}{star}")
+    }
+
+    /// Same as [`show_detail`](DiaryEntry::show_detail) but the content is
+    /// rendered as Markdown, wrapped to `width` columns
+    pub fn show_rendered(&self, width: usize) -> String {
+        format!(
+            "{}:\n{}{}",
+            self.show_header(),
+            crate::markdown::render(&self.content, width),
+            self.show_footer()
+        )
+    }
+
+    /// Same as [`show_detail`](DiaryEntry::show_detail) but the content is left
+    /// exactly as it was written
+    pub fn show_raw(&self) -> String {
+        format!(
+            "{}:\n{}{}",
+            self.show_header(),
+            self.content,
+            self.show_footer()
+        )
+    }
+
+    /// The session, ratings, references to code and attached files listed
+    /// under the content, if there are any
+    fn show_footer(&self) -> String {
+        let section = |heading: &str, lines: Vec<String>| -> String {
+            let lines: String = lines.iter().map(|line| format!("\n  {line}")).collect();
+            match lines.is_empty() {
+                true => lines,
+                false => format!("\n\n{}{lines}", paint(Role::Muted, heading)),
+            }
+        };
+        let refs = self
+            .refs
+            .iter()
+            .map(|file_ref| paint(Role::Link, &file_ref.to_string()))
+            .collect();
+        let attachments = self
+            .attachments
+            .iter()
+            .map(|attachment| {
+                let size = format!("({})", show_size(attachment.size));
+                format!("{} {}", attachment.name, paint(Role::Muted, &size))
+            })
+            .collect();
+        let session = match (self.duration(), self.ended) {
+            (Some(duration), Some(ended)) => vec![format!(
+                "{} {}",
+                paint(Role::Accent, &show_duration(duration)),
+                paint(
+                    Role::Muted,
+                    &format!("({} to {})", self.date.display(), ended.display())
+                )
+            )],
+            _ => Vec::new(),
+        };
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, value)| {
+                let rating = paint(Role::Accent, &value.to_string());
+                format!("{name} {rating}{}", paint(Role::Muted, "/5"))
+            })
+            .collect();
+        format!(
+            "{}{}{}{}",
+            section("Session:", session),
+            section("Fields:", fields),
+            section("Refs:", refs),
+            section("Attachments:", attachments)
+        )
+    }
+
+    /// The colorful title, date and tags line shared by the detailed formats
+    fn show_header(&self) -> String {
         let formatted_title = match &self.title {
-            Some(title) => color_print::cformat!("<blue, bold>{}</>", title),
-            None => color_print::cformat!("<white>Untitled</>"),
+            Some(title) => paint(Role::Title, title),
+            None => paint(Role::Untitled, "Untitled"),
         };
-        color_print::cformat!(
-            "{} <magenta>(</><red>{}</><magenta>)</>:\n<yellow>{}</>",
+        let formatted_tags: String = self
+            .tags
+            .iter()
+            .map(|tag| format!(" {}", paint(Role::Tag, &format!("#{tag}"))))
+            .collect();
+        format!(
+            "{} {}{}{}{}",
             formatted_title,
-            self.date,
-            self.content,
+            paint(Role::Punctuation, "("),
+            paint(Role::Date, &self.date.display()),
+            paint(Role::Punctuation, ")"),
+            formatted_tags,
         )
     }
 }
@@ -135,6 +243,53 @@ impl std::fmt::Display for DiaryEntry {
             None => String::from("Untitled"),
         };
 
-        write!(f, "{} ({})", title, self.date)
+        write!(f, "{} ({})", title, self.date.display())
+    }
+}
+
+#[cfg(test)]
+impl DiaryEntry {
+    /// An otherwise empty entry written at `date`, given as `YYYY-MM-DD
+    /// HH:MM:SS`, for tests
+    pub fn at(date: &str) -> Self {
+        Self {
+            date: TimeStamp::from_string(date).unwrap(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `append` and returns the times it could have stamped, in case
+    /// the minute turns over
+    fn stamped(append: impl FnOnce()) -> [String; 2] {
+        let now = || chrono::Local::now().format("%H:%M").to_string();
+        let before = now();
+        append();
+        [before, now()]
+    }
+
+    #[test]
+    fn append_line_starts_an_empty_entry() {
+        let mut entry = DiaryEntry::default();
+        let times = stamped(|| entry.append_line("  fixed the flaky test \n"));
+        assert!(times
+            .iter()
+            .any(|now| entry.content == format!("- {now} fixed the flaky test")));
+    }
+
+    #[test]
+    fn append_line_adds_below_the_content() {
+        let mut entry = DiaryEntry {
+            content: String::from("# Monday\n- 09:00 standup\n\n\n"),
+            ..Default::default()
+        };
+        let times = stamped(|| entry.append_line("review"));
+        assert!(times
+            .iter()
+            .any(|now| entry.content == format!("# Monday\n- 09:00 standup\n- {now} review")));
     }
 }
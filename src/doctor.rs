@@ -0,0 +1,267 @@
+//! Finds and repairs damage to the database for `code_diary doctor`: a
+//! corrupt file, entries that cannot be read, entries sharing an id and rows
+//! left behind by deleted entries. Entries that cannot be repaired are moved
+//! to the `Quarantine` table rather than deleted, and their tags, attachments
+//! and other rows are kept.
+
+use crate::backend;
+
+#[derive(Debug, Clone, PartialEq)]
+/// Something wrong with the database, found by
+/// [`diagnose`](crate::backend::diagnose). Entries are identified by their
+/// `rowid`, since their ids may be missing or shared.
+pub enum Problem {
+    /// `PRAGMA integrity_check` found damage to the file itself
+    Corruption(String),
+    /// An entry has no id, or one that is not a number
+    BadId {
+        /// The row of the entry
+        rowid: i64,
+    },
+    /// An entry's timestamp is missing or not a real date and time
+    BadTimestamp {
+        /// The row of the entry
+        rowid: i64,
+        /// The id of the entry, if it has one
+        id: Option<i64>,
+        /// The timestamp as stored
+        timestamp: Option<String>,
+    },
+    /// An entry's title, content or notebook is missing
+    Null {
+        /// The row of the entry
+        rowid: i64,
+        /// The id of the entry, if it has one
+        id: Option<i64>,
+        /// The missing column
+        column: &'static str,
+    },
+    /// Several entries share an id
+    DuplicateId {
+        /// The shared id
+        id: i64,
+        /// The rows of every entry with the id but the oldest
+        rowids: Vec<i64>,
+    },
+    /// Rows in another table that belong to no entry
+    Orphans {
+        /// The table
+        table: &'static str,
+        /// How many rows belong to no entry
+        count: i64,
+    },
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Corruption(damage) => write!(f, "The database file is damaged: {damage}"),
+            Self::BadId { rowid } => write!(f, "The entry in row {rowid} has no usable id"),
+            Self::BadTimestamp {
+                rowid,
+                id,
+                timestamp,
+            } => {
+                let timestamp = match timestamp {
+                    Some(timestamp) => format!("an unreadable timestamp '{timestamp}'"),
+                    None => String::from("no timestamp"),
+                };
+                write!(f, "{} has {timestamp}", entry(*rowid, *id))
+            }
+            Self::Null { rowid, id, column } => {
+                let column = column.to_lowercase();
+                write!(f, "{} has no {column}", entry(*rowid, *id))
+            }
+            Self::DuplicateId { id, rowids } => match rowids.len() {
+                1 => write!(f, "2 entries share the id {id}"),
+                count => write!(f, "{} entries share the id {id}", count + 1),
+            },
+            Self::Orphans { table, count } => match count {
+                1 => write!(f, "1 row in {table} belongs to no entry"),
+                count => write!(f, "{count} rows in {table} belong to no entry"),
+            },
+        }
+    }
+}
+
+/// Describes the entry in row `rowid` by its id, if it has one
+fn entry(rowid: i64, id: Option<i64>) -> String {
+    match id {
+        Some(id) => format!("Entry {id}"),
+        None => format!("The entry in row {rowid}"),
+    }
+}
+
+/// Repairs `problems` where possible, returning what was done about each.
+/// Entries with unreadable timestamps are quarantined, entries without a
+/// usable id or sharing one get a new id, missing titles and contents are
+/// left empty, and rows belonging to no entry are removed. A damaged file
+/// cannot be repaired here.
+///
+/// An entry given a new id for lack of a usable one takes its tags,
+/// attachments and other rows with it. When entries share an id there is no
+/// telling whose rows are whose, so they all stay with the oldest entry.
+pub fn repair(problems: &[Problem]) -> Vec<String> {
+    let quarantined: Vec<i64> = problems
+        .iter()
+        .filter_map(|problem| match problem {
+            Problem::BadTimestamp { rowid, .. } => Some(*rowid),
+            _ => None,
+        })
+        .collect();
+    let mut done = Vec::new();
+    for problem in problems {
+        match problem {
+            Problem::Corruption(_) => done.push(String::from(
+                "Left the damage to the file; restore a backup with `code_diary restore`",
+            )),
+            // Nothing else is done to entries that are moved out of the way
+            Problem::BadId { rowid } | Problem::Null { rowid, .. }
+                if quarantined.contains(rowid) => {}
+            Problem::BadId { rowid } => {
+                let id = backend::renumber_entry(*rowid, true);
+                done.push(format!("Gave the entry in row {rowid} the id {id}"));
+            }
+            Problem::BadTimestamp { rowid, id, .. } => {
+                backend::quarantine_entry(*rowid, &problem.to_string());
+                let entry = entry(*rowid, *id).to_lowercase();
+                done.push(format!("Moved {entry} to the Quarantine table"));
+            }
+            Problem::Null { rowid, id, column } => {
+                backend::fill_in_entry(*rowid, column);
+                let column = column.to_lowercase();
+                let entry = entry(*rowid, *id).to_lowercase();
+                done.push(format!("Filled in the {column} of {entry}"));
+            }
+            Problem::DuplicateId { id, rowids } => {
+                for rowid in rowids.iter().filter(|rowid| !quarantined.contains(rowid)) {
+                    let new_id = backend::renumber_entry(*rowid, false);
+                    done.push(format!(
+                        "Gave a copy of entry {id} the id {new_id}; its tags, attachments and \
+                         other rows stay with entry {id}"
+                    ));
+                }
+            }
+            // Found again below, once entries have been moved and renumbered
+            Problem::Orphans { .. } => (),
+        }
+    }
+    for problem in backend::diagnose() {
+        if let Problem::Orphans { table, .. } = problem {
+            let removed = match backend::remove_orphans(table) {
+                1 => String::from("1 row"),
+                count => format!("{count} rows"),
+            };
+            done.push(format!(
+                "Removed {removed} from {table} that belonged to no entry"
+            ));
+        }
+    }
+    done
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `query` against the database, returning each row's values joined
+    /// by `|`
+    fn rows(db: &sqlite::Connection, query: &str) -> Vec<String> {
+        let mut rows = Vec::new();
+        db.iterate(query, |pairs| {
+            let values: Vec<&str> = pairs
+                .iter()
+                .map(|(_, value)| value.unwrap_or("NULL"))
+                .collect();
+            rows.push(values.join("|"));
+            true
+        })
+        .unwrap();
+        rows
+    }
+
+    // The database path can only be set once, so every repair is checked
+    // against this one fixture
+    #[test]
+    fn repair_fixes_a_damaged_diary_without_losing_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        backend::set_db_path(dir.path().join("code_diary.db"));
+        assert_eq!(backend::diagnose(), Vec::new());
+
+        let db = sqlite::open(backend::db_path()).unwrap();
+        db.execute(
+            "INSERT INTO Entries (rowid, Id, TimeStamp, Title, Content) VALUES
+                 (1, 1, '2022-03-01 09:00:00', 'One', 'first'),
+                 (2, 2, '2022-02-30 10:00:00', NULL, 'bad date'),
+                 (3, 3, '2022-03-02 09:00:00', 'Three', 'original'),
+                 (4, 3, '2022-03-02 10:00:00', 'Copy', 'copy'),
+                 (5, 'abc', '2022-03-03 09:00:00', 'Text id', 'text id'),
+                 (6, 4, '2022-03-04 09:00:00', NULL, 'untitled');
+             INSERT INTO Tags VALUES (1, 'one'), (2, 'lost'), (3, 'dup'), ('abc', 'moved'),
+                 (99, 'orphan');
+             INSERT INTO Fields VALUES (2, 'mood', 3);
+             INSERT INTO EntryLinks VALUES (1, 2);",
+        )
+        .unwrap();
+
+        let problems = backend::diagnose();
+        assert_eq!(
+            problems,
+            vec![
+                Problem::BadTimestamp {
+                    rowid: 2,
+                    id: Some(2),
+                    timestamp: Some(String::from("2022-02-30 10:00:00")),
+                },
+                Problem::Null {
+                    rowid: 2,
+                    id: Some(2),
+                    column: "Title",
+                },
+                Problem::BadId { rowid: 5 },
+                Problem::Null {
+                    rowid: 6,
+                    id: Some(4),
+                    column: "Title",
+                },
+                Problem::DuplicateId {
+                    id: 3,
+                    rowids: vec![4],
+                },
+                Problem::Orphans {
+                    table: "Tags",
+                    count: 1,
+                },
+            ]
+        );
+
+        assert_eq!(
+            repair(&problems),
+            vec![
+                "Moved entry 2 to the Quarantine table",
+                "Gave the entry in row 5 the id 5",
+                "Filled in the title of entry 4",
+                "Gave a copy of entry 3 the id 6; its tags, attachments and other rows stay \
+                 with entry 3",
+                "Removed 1 row from Tags that belonged to no entry",
+            ]
+        );
+        assert_eq!(backend::diagnose(), Vec::new());
+
+        // The quarantined entry keeps its tags, fields and links
+        assert_eq!(
+            rows(&db, "SELECT Id, TimeStamp, Content FROM Quarantine;"),
+            vec!["2|2022-02-30 10:00:00|bad date"]
+        );
+        assert_eq!(
+            rows(&db, "SELECT EntryId, Tag FROM Tags ORDER BY Tag;"),
+            vec!["3|dup", "2|lost", "5|moved", "1|one"]
+        );
+        assert_eq!(rows(&db, "SELECT * FROM Fields;"), vec!["2|mood|3"]);
+        assert_eq!(rows(&db, "SELECT * FROM EntryLinks;"), vec!["1|2"]);
+        assert_eq!(
+            rows(&db, "SELECT rowid, Id, Title FROM Entries ORDER BY rowid;"),
+            vec!["1|1|One", "3|3|Three", "4|6|Copy", "5|5|Text id", "6|4|"]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! Ratings such as mood, energy or focus, from 1 to 5, kept with entries for
+//! personal retros. Which fields there are is up to the user, e.g.
+//! `code_diary config set fields mood,energy,focus`; `add` then asks for each
+//! of them, and `code_diary stats --field mood` charts one over time.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+
+use crate::diaryentry::DiaryEntry;
+use crate::fail;
+
+/// The lowest rating
+pub const MIN: u8 = 1;
+
+/// The highest rating
+pub const MAX: u8 = 5;
+
+/// The bars of a sparkline, from lowest to highest
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Checks that `name` can be used as a field name
+/// # Panics
+/// Will 'gracefully' exit if it cannot
+pub fn check_name(name: &str) {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        fail!("'{name}' is not a valid field name; use letters, digits, - and _");
+    }
+}
+
+/// Parses a rating given as `name=value`, e.g. `mood=4`, for one of the
+/// configured fields `names`
+/// # Panics
+/// Will 'gracefully' exit if the field is not configured or the value is not
+/// a rating
+pub fn parse(arg: &str, names: &[String]) -> (String, u8) {
+    let Some((name, value)) = arg.split_once('=') else {
+        fail!("Expected a field like mood=4, got '{arg}'");
+    };
+    let name = name.trim();
+    if !names.iter().any(|known| known == name) {
+        match names.is_empty() {
+            true => fail!("No fields are set up; add some with `config set fields mood,energy`"),
+            false => {
+                let names = names.join(", ");
+                fail!("Unknown field '{name}'; expected one of {names}")
+            }
+        }
+    }
+    match value.trim().parse::<u8>() {
+        Ok(value) if (MIN..=MAX).contains(&value) => (name.to_string(), value),
+        _ => fail!("{name} must be a number from {MIN} to {MAX}"),
+    }
+}
+
+/// Puts `fields` in the order of the configured `names`
+pub fn sort(fields: &mut [(String, u8)], names: &[String]) {
+    fields.sort_by_key(|(name, _)| names.iter().position(|known| known == name));
+}
+
+/// Returns the average rating of `field` on each day it was rated, oldest
+/// first
+pub fn daily_averages(entries: &[DiaryEntry], field: &str) -> Vec<(NaiveDate, f64)> {
+    let mut days: BTreeMap<NaiveDate, Vec<u8>> = BTreeMap::new();
+    for entry in entries {
+        let Some(day) = entry.date.to_datetime().map(|date| date.date()) else {
+            continue;
+        };
+        for (_, value) in entry.fields.iter().filter(|(name, _)| name == field) {
+            days.entry(day).or_default().push(*value);
+        }
+    }
+    days.into_iter()
+        .map(|(day, values)| {
+            let total: u32 = values.iter().map(|&value| u32::from(value)).sum();
+            (day, f64::from(total) / values.len() as f64)
+        })
+        .collect()
+}
+
+/// Draws ratings as a sparkline, one bar each
+pub fn sparkline(values: &[f64]) -> String {
+    let steps = (BARS.len() - 1) as f64;
+    values
+        .iter()
+        .map(|value| {
+            let height = (value - f64::from(MIN)) / f64::from(MAX - MIN);
+            BARS[(height.clamp(0.0, 1.0) * steps).round() as usize]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        vec![String::from("mood"), String::from("energy")]
+    }
+
+    #[test]
+    fn parse_reads_configured_ratings() {
+        assert_eq!(parse("mood=4", &names()), (String::from("mood"), 4));
+        assert_eq!(parse(" energy = 1 ", &names()), (String::from("energy"), 1));
+    }
+
+    #[test]
+    fn sort_follows_the_configured_order() {
+        let mut fields = vec![(String::from("energy"), 2), (String::from("mood"), 5)];
+        sort(&mut fields, &names());
+        assert_eq!(
+            fields,
+            vec![(String::from("mood"), 5), (String::from("energy"), 2)]
+        );
+    }
+
+    #[test]
+    fn daily_averages_average_each_day_oldest_first() {
+        let entry = |date, fields: &[(&str, u8)]| DiaryEntry {
+            fields: fields
+                .iter()
+                .map(|(name, value)| (name.to_string(), *value))
+                .collect(),
+            ..DiaryEntry::at(date)
+        };
+        let entries = [
+            entry("2022-03-02 09:00:00", &[("mood", 2)]),
+            entry("2022-03-01 09:00:00", &[("mood", 4), ("energy", 1)]),
+            entry("2022-03-01 18:00:00", &[("mood", 5)]),
+            entry("2022-03-03 09:00:00", &[("energy", 3)]),
+        ];
+        let day = |day: &str| NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap();
+        assert_eq!(
+            daily_averages(&entries, "mood"),
+            vec![(day("2022-03-01"), 4.5), (day("2022-03-02"), 2.0)]
+        );
+        assert!(daily_averages(&entries, "focus").is_empty());
+    }
+
+    #[test]
+    fn sparkline_spans_the_rating_range() {
+        assert_eq!(sparkline(&[1.0, 3.0, 5.0]), "▁▅█");
+        assert_eq!(sparkline(&[0.0, 2.0, 9.0]), "▁▃█");
+        assert_eq!(sparkline(&[]), "");
+    }
+}
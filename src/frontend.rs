@@ -1,9 +1,23 @@
 //! Contains functions for handling direct user input without CLI arguments.
 
+use std::collections::BTreeSet;
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame, TerminalOptions, Viewport};
+
 use crate::diaryentry::DiaryEntry;
+use crate::fail;
+use crate::theme::{self, Role};
 use crate::Action;
 use crate::Add;
 use crate::Delete;
+use crate::Edit;
 use crate::View;
 
 macro_rules! prompt_list {
@@ -22,8 +36,15 @@ macro_rules! prompt_list {
 }
 
 macro_rules! prompt_text {
-    ($name:ident with message $msg:expr) => {
-        let question = requestty::Question::input($msg).message($msg).build();
+    ($name:ident with message $msg:literal) => {
+        prompt_text!($name with message $msg with default "");
+    };
+
+    ($name:ident with message $msg:literal with default $default:expr) => {
+        let question = requestty::Question::input($msg)
+            .message($msg)
+            .default($default)
+            .build();
         let answer = requestty::prompt_one(question).unwrap();
         let $name = answer.as_string().unwrap();
     };
@@ -37,81 +58,504 @@ macro_rules! prompt_confirm {
     };
 }
 
-/// Interactively asks a user where they want to Add, Delete, or View.
+/// Interactively asks a user where they want to Add, Delete, Edit, or View.
 /// Example:
 /// ```
 /// # use crate::backend::prompt_action;
 /// # use crate::Action;
 /// # fn main()
 /// match prompt_action() {
-///     Action::Add(_) | Action::Delete(_) | Action::View(_) => (),
+///     Action::Add(_) | Action::Delete(_) | Action::Edit(_) | Action::View(_) => (),
 ///     _ => unreachable!(),
 /// }
 pub fn prompt_action() -> Action {
     prompt_list! { action
-        with choices ["Add", "Delete", "View"]
+        with choices ["Add", "Delete", "Edit", "View"]
         with message "What do you want to do?"
     };
 
     const ADD: usize = 0;
     const DELETE: usize = 1;
-    const VIEW: usize = 2;
+    const EDIT: usize = 2;
+    const VIEW: usize = 3;
 
     match action {
         ADD => Action::Add(Add {
             title: None,
             content: None,
+            tag: Vec::new(),
+            template: None,
+            refs: Vec::new(),
+            attach: Vec::new(),
+            field: Vec::new(),
+        }),
+        DELETE => Action::Delete(Delete {
+            ids: Vec::new(),
+            tag: None,
+            before: None,
+            since: None,
+            yes: false,
+        }),
+        EDIT => Action::Edit(Edit {
+            id: None,
+            title: None,
+            content: None,
+        }),
+        VIEW => Action::View(View {
+            id: None,
+            raw: false,
         }),
-        DELETE => Action::Delete(Delete { index: None }),
-        VIEW => Action::View(View { index: None }),
         _ => unreachable!(),
     }
 }
 
+/// Asks the user which notebook to work in, given the notebooks and their entry
+/// counts as returned by [`get_notebooks`](crate::backend::get_notebooks).
+pub fn prompt_for_notebook(notebooks: &[(String, u64)]) -> String {
+    let choices: Vec<String> = notebooks
+        .iter()
+        .map(|(name, count)| format!("{name} ({count})"))
+        .collect();
+    prompt_list! { index
+        with choices [choices]
+        with message "Which notebook do you want to use?"
+    };
+    notebooks[index].0.clone()
+}
+
+/// Asks the user which of `days`, given with their entry counts, they want to
+/// see the entries of. Returns `None` if they want none of them.
+pub fn prompt_for_day(days: &[(chrono::NaiveDate, usize)]) -> Option<chrono::NaiveDate> {
+    let mut choices: Vec<String> = days
+        .iter()
+        .map(|(day, count)| match count {
+            1 => format!("{} (1 entry)", day.format("%a %Y-%m-%d")),
+            count => format!("{} ({count} entries)", day.format("%a %Y-%m-%d")),
+        })
+        .collect();
+    choices.push(String::from("None, I'm done"));
+    prompt_list! { index
+        with choices [choices]
+        with message "Which day do you want to see?"
+    };
+    days.get(index).map(|(day, _)| *day)
+}
+
 /// Prompts the user for a title.
 pub fn prompt_for_title() -> Option<String> {
+    prompt_for_title_or("")
+}
+
+/// Same as [`prompt_for_title`] but `default` is used if the user enters
+/// nothing, e.g. a template's title.
+pub fn prompt_for_title_or(default: &str) -> Option<String> {
     prompt_text! {
         title with message
         "Please enter a title (optional) for your diary entry)"
+        with default default
     };
 
-    if title.len() == 0 {
+    if title.is_empty() {
         return None;
     }
 
     Some(title.to_string())
 }
 
-/// Same as [`prompt_for_viewing`](crate::frontend::prompt_for_viewing) but the
-/// message is different and there is a confirmation step.
-pub fn prompt_for_deletion(options: &Vec<DiaryEntry>) -> u64 {
-    prompt_list! { index
-        with choices [options]
-        with message "Which item do you want to replace?"
-    };
+/// Asks the user to rate each of the fields called `names` from 1 to 5, or
+/// skip it. Returns the ratings given.
+pub fn prompt_for_fields(names: &[String]) -> Vec<(String, u8)> {
+    let mut fields = Vec::new();
+    for name in names {
+        let message = format!("How would you rate your {name}?");
+        prompt_list! { index
+            with choices ["Skip", "1", "2", "3", "4", "5"]
+            with message message.as_str()
+        };
+        if index > 0 {
+            fields.push((name.clone(), index as u8));
+        }
+    }
+    fields
+}
 
-    prompt_confirm!(user_is_sure with message "Are you sure?");
+/// Lets the user tick any number of `options` to delete and asks for
+/// confirmation. Returns the indices of the chosen entries.
+pub fn prompt_for_deletion(options: &[DiaryEntry]) -> Vec<usize> {
+    let indices = prompt_for_selection(options, "Which items do you want to delete?");
+    let chosen: Vec<&DiaryEntry> = indices.iter().map(|&index| &options[index]).collect();
 
-    if !user_is_sure {
+    if !confirm_selection(&chosen, "delete") {
         std::process::exit(0);
     }
 
-    println!("Index from prompt_for_deletion: {index}");
+    indices
+}
+
+/// Lets the user tick any number of `options` in the entry picker, returning
+/// their indices. Exits if nothing was ticked.
+pub fn prompt_for_selection(options: &[DiaryEntry], message: &str) -> Vec<usize> {
+    match pick_entries(options, message) {
+        Some(indices) if !indices.is_empty() => indices,
+        _ => {
+            println!("Nothing selected.");
+            std::process::exit(0);
+        }
+    }
+}
+
+/// Shows how many and which entries are about to be acted on, e.g. with
+/// `verb` set to `"delete"`, and asks the user to confirm.
+pub fn confirm_selection(entries: &[&DiaryEntry], verb: &str) -> bool {
+    println!("About to {verb} {} entries:", entries.len());
+    for entry in entries {
+        println!("  {:>4}  {entry}", entry.id);
+    }
+    prompt_confirm!(user_is_sure with message "Are you sure?");
+    user_is_sure
+}
+
+/// Asks whether to record an entry about the commit called `subject`, for
+/// the git hook
+pub fn prompt_to_record_commit(subject: &str) -> bool {
+    println!("Committed: {subject}");
+    prompt_confirm!(record with message "Write a diary entry about it?");
+    record
+}
 
-    index as u64
+/// Asks whether to replace every entry in the diary with the ones in the
+/// backup `file`
+pub fn prompt_to_restore(file: &str) -> bool {
+    println!("This replaces every entry in the diary with the ones in {file}.");
+    prompt_confirm!(restore with message "Restore it?");
+    restore
+}
+
+/// Asks whether to delete the `count` backups taken before the diary was
+/// encrypted, which still hold its entries in plaintext
+pub fn prompt_to_delete_plaintext_backups(count: usize) -> bool {
+    match count {
+        1 => println!("A backup taken before encryption still holds your entries in plaintext."),
+        count => println!(
+            "{count} backups taken before encryption still hold your entries in plaintext."
+        ),
+    }
+    prompt_confirm!(delete with message "Delete them?");
+    delete
 }
 
 /// Takes in a list of `DiaryEntry`s and selects the index that the user choose
-pub fn prompt_for_viewing(options: &Vec<DiaryEntry>) -> u64 {
-    prompt_list! { index
-        with choices [options]
-        with message "Which item do you want to view?"
+pub fn prompt_for_viewing(options: &[DiaryEntry]) -> u64 {
+    pick_or_exit(options, "Which item do you want to view?")
+}
+
+/// Same as [`prompt_for_viewing`](crate::frontend::prompt_for_viewing) but the
+/// message is different.
+pub fn prompt_for_editing(options: &[DiaryEntry]) -> u64 {
+    pick_or_exit(options, "Which item do you want to edit?")
+}
+
+fn pick_or_exit(options: &[DiaryEntry], message: &str) -> u64 {
+    match pick_entry(options, message) {
+        Some(index) => index as u64,
+        None => std::process::exit(0),
+    }
+}
+
+/// How many lines of the terminal the entry picker takes up
+const PICKER_HEIGHT: u16 = 16;
+
+/// Lets the user choose one of `entries`, filtering them as they type by
+/// fuzzy-matching their title, date, tags and content. The matches are paged,
+/// and the highlighted entry's content is previewed next to them. Returns the
+/// index of the chosen entry in `entries`, or `None` if the user cancelled.
+/// # Panics
+/// Will 'gracefully' exit if the terminal cannot be used
+pub fn pick_entry(entries: &[DiaryEntry], message: &str) -> Option<usize> {
+    run_picker(entries, message, false).and_then(|chosen| chosen.first().copied())
+}
+
+/// Same as [`pick_entry`], but the user ticks any number of entries with Tab
+/// before pressing Enter. Returns the indices of the ticked entries in
+/// `entries`, or of the highlighted one if none were ticked.
+/// # Panics
+/// Will 'gracefully' exit if the terminal cannot be used
+pub fn pick_entries(entries: &[DiaryEntry], message: &str) -> Option<Vec<usize>> {
+    run_picker(entries, message, true)
+}
+
+fn run_picker(entries: &[DiaryEntry], message: &str, multiple: bool) -> Option<Vec<usize>> {
+    let options = TerminalOptions {
+        viewport: Viewport::Inline(PICKER_HEIGHT),
     };
-    index as u64
+    let mut terminal = match ratatui::try_init_with_options(options) {
+        Ok(terminal) => terminal,
+        Err(e) => fail!("Terminal Error: {e}"),
+    };
+    let mut picker = Picker::new(entries, message, multiple);
+    let result = picker.run(&mut terminal);
+    let _ = terminal.clear();
+    ratatui::restore();
+    match result {
+        Ok(choice) => choice,
+        Err(e) => fail!("Terminal Error: {e}"),
+    }
+}
+
+struct Picker<'a> {
+    entries: &'a [DiaryEntry],
+    message: &'a str,
+    /// The text each entry is matched against
+    haystacks: Vec<String>,
+    query: String,
+    /// Indices into `entries` that match `query`, best match first
+    matches: Vec<usize>,
+    selected: usize,
+    page_size: usize,
+    /// Whether several entries can be ticked
+    multiple: bool,
+    /// Indices into `entries` of the ticked entries
+    ticked: BTreeSet<usize>,
+}
+
+impl<'a> Picker<'a> {
+    fn new(entries: &'a [DiaryEntry], message: &'a str, multiple: bool) -> Self {
+        let haystacks = entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{} {} {} {}",
+                    entry.title.as_deref().unwrap_or("Untitled"),
+                    entry.date.display(),
+                    entry.tags.join(" "),
+                    entry.content,
+                )
+            })
+            .collect();
+        let mut picker = Self {
+            entries,
+            message,
+            haystacks,
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            page_size: 1,
+            multiple,
+            ticked: BTreeSet::new(),
+        };
+        picker.filter();
+        picker
+    }
+
+    fn filter(&mut self) {
+        let matcher = SkimMatcherV2::default();
+        let mut scored: Vec<(i64, usize)> = self
+            .haystacks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, haystack)| match self.query.is_empty() {
+                true => Some((0, index)),
+                false => Some((matcher.fuzzy_match(haystack, &self.query)?, index)),
+            })
+            .collect();
+        // Without a query, show the pinned entries and then the newest first
+        scored.reverse();
+        scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        if self.query.is_empty() {
+            scored.sort_by_key(|&(_, index)| !self.entries[index].pinned);
+        }
+        self.matches = scored.into_iter().map(|(_, index)| index).collect();
+        self.selected = 0;
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<Option<Vec<usize>>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            let control = key.modifiers.contains(KeyModifiers::CONTROL);
+            let last = self.matches.len().saturating_sub(1);
+            match key.code {
+                KeyCode::Enter if !self.ticked.is_empty() => {
+                    return Ok(Some(self.ticked.iter().copied().collect()))
+                }
+                KeyCode::Enter => {
+                    let highlighted = self.matches.get(self.selected).copied();
+                    return Ok(highlighted.map(|index| vec![index]));
+                }
+                KeyCode::Tab if self.multiple => self.toggle(),
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Char('c') if control => return Ok(None),
+                KeyCode::Char('n') if control => self.selected = (self.selected + 1).min(last),
+                KeyCode::Char('p') if control => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down => self.selected = (self.selected + 1).min(last),
+                KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+                KeyCode::PageDown => self.selected = (self.selected + self.page_size).min(last),
+                KeyCode::PageUp => self.selected = self.selected.saturating_sub(self.page_size),
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.filter();
+                }
+                KeyCode::Char(c) if !control => {
+                    self.query.push(c);
+                    self.filter();
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Ticks the highlighted entry, or unticks it if it is already ticked
+    fn toggle(&mut self) {
+        let Some(&index) = self.matches.get(self.selected) else {
+            return;
+        };
+        if !self.ticked.remove(&index) {
+            self.ticked.insert(index);
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [prompt, body] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(3)]).areas(frame.area());
+        let [list_area, preview_area] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(body);
+
+        let prompt_line = Line::from(vec![
+            Span::styled("? ", theme::style(Role::Accent)),
+            Span::styled(self.message, Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" › "),
+            Span::raw(self.query.as_str()),
+        ]);
+        frame.render_widget(Paragraph::new(prompt_line), prompt);
+
+        self.page_size = (list_area.height as usize).saturating_sub(2).max(1);
+        let page = self.selected / self.page_size;
+        let pages = self.matches.len().div_ceil(self.page_size).max(1);
+        let items: Vec<ListItem> = self
+            .matches
+            .iter()
+            .skip(page * self.page_size)
+            .take(self.page_size)
+            .map(|&index| {
+                let entry = &self.entries[index];
+                let mut line = match (self.multiple, self.ticked.contains(&index)) {
+                    (false, _) => String::new(),
+                    (true, true) => String::from("[x] "),
+                    (true, false) => String::from("[ ] "),
+                };
+                line.push_str(&entry.to_string());
+                for tag in &entry.tags {
+                    line.push_str(&format!(" #{tag}"));
+                }
+                line.push_str(&entry.marks());
+                ListItem::new(line)
+            })
+            .collect();
+        let mut state = ListState::default().with_selected(match self.matches.is_empty() {
+            true => None,
+            false => Some(self.selected - page * self.page_size),
+        });
+        let ticked = match self.multiple {
+            true => format!("{} ticked (Tab), ", self.ticked.len()),
+            false => String::new(),
+        };
+        let title = format!(
+            " {} matches, {ticked}page {}/{} ",
+            self.matches.len(),
+            page + 1,
+            pages
+        );
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut state);
+
+        let preview = match self.matches.get(self.selected) {
+            Some(&index) => self.entries[index].content.as_str(),
+            None => "",
+        };
+        let preview = Paragraph::new(preview)
+            .block(Block::default().borders(Borders::ALL).title(" Preview "))
+            .wrap(Wrap { trim: false });
+        frame.render_widget(preview, preview_area);
+    }
 }
 
 /// Prompts for the body of a `DiaryEntry`
 pub fn prompt_for_content() -> String {
-    prompt_text!(content with message "Please write your diary entry");
+    prompt_for_content_or("")
+}
+
+/// Same as [`prompt_for_content`] but `default` is used if the user enters
+/// nothing.
+pub fn prompt_for_content_or(default: &str) -> String {
+    prompt_text!(content with message "Please write your diary entry" with default default);
     content.to_string()
 }
+
+/// Opens `editor` on a temporary file containing `initial` and returns what
+/// the user saved. `editor` may contain arguments, e.g. `code --wait`. The file
+/// has a random name, only the user can read it, and it is removed afterwards
+/// whatever happens.
+/// # Panics
+/// Will 'gracefully' exit if the editor cannot be started or fails
+pub fn prompt_for_content_in_editor(editor: &str, initial: &str) -> String {
+    let file = tempfile::Builder::new()
+        .prefix("code_diary-")
+        .suffix(".md")
+        .tempfile();
+    let file = match file {
+        Ok(file) => file,
+        Err(e) => fail!("Could not create a temporary file: {e}"),
+    };
+
+    let content = std::fs::write(file.path(), initial)
+        .map_err(|e| format!("Could not write the temporary file: {e}"))
+        .and_then(|_| run_editor(editor, file.path()))
+        .and_then(|success| match success {
+            true => std::fs::read_to_string(file.path())
+                .map_err(|e| format!("Could not read the entry back: {e}")),
+            false => Err(String::from(
+                "The editor exited with an error, discarding the entry",
+            )),
+        });
+    // `fail!` exits without running destructors, so remove the file first
+    drop(file);
+
+    match content {
+        Ok(content) => content.trim_end().to_string(),
+        Err(e) => fail!("{e}"),
+    }
+}
+
+/// Opens `path` in `editor` and waits for it to close. Returns whether the
+/// editor exited successfully.
+/// # Panics
+/// Will 'gracefully' exit if the editor cannot be started
+pub fn edit_file(editor: &str, path: &std::path::Path) -> bool {
+    match run_editor(editor, path) {
+        Ok(success) => success,
+        Err(e) => fail!("{e}"),
+    }
+}
+
+/// Same as [`edit_file`], but returns why the editor could not be started
+/// instead of exiting
+fn run_editor(editor: &str, path: &std::path::Path) -> Result<bool, String> {
+    let mut words = editor.split_whitespace();
+    let Some(program) = words.next() else {
+        return Err(String::from("The configured editor is empty!"));
+    };
+    match std::process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+    {
+        Ok(status) => Ok(status.success()),
+        Err(e) => Err(format!("Could not start the editor '{program}': {e}")),
+    }
+}
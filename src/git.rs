@@ -0,0 +1,129 @@
+//! Asks `git` about the repository the user is working in, so entries can
+//! remember which project they were written for.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use chrono::{DateTime, Local, NaiveDateTime};
+
+/// Runs `git` with `args` in `dir` (or the current directory) and returns its
+/// trimmed output, or `None` if git is missing or the command fails
+pub fn git(dir: Option<&Path>, args: &[&str]) -> Option<String> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = command.args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8(output.stdout).ok()?;
+    Some(text.trim_end().to_string())
+}
+
+/// Returns the top level directory of the repository around the current
+/// directory, if there is one
+pub fn root() -> Option<PathBuf> {
+    git(None, &["rev-parse", "--show-toplevel"]).map(PathBuf::from)
+}
+
+/// Returns the name of the repository around the current directory, i.e. the
+/// name of its top level directory
+pub fn repo_name() -> Option<String> {
+    let root = root()?;
+    Some(root.file_name()?.to_string_lossy().into_owned())
+}
+
+/// Returns the branch checked out in the repository around the current
+/// directory, or `None` outside a repository or on a detached HEAD
+pub fn branch() -> Option<String> {
+    git(None, &["symbolic-ref", "--quiet", "--short", "HEAD"])
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// What `hook run` records about a commit
+pub struct Commit {
+    /// The full hash, which `import --git` also identifies commits by
+    pub hash: String,
+    /// The abbreviated hash
+    pub short_hash: String,
+    /// The first line of the message
+    pub subject: String,
+    /// The paths of the files it changed
+    pub files: Vec<String>,
+    /// The summary line of its diffstat, e.g. `2 files changed, 9 insertions(+)`
+    pub stat: String,
+}
+
+/// Returns the commit checked out in the repository around the current
+/// directory
+pub fn last_commit() -> Option<Commit> {
+    let hash = git(None, &["rev-parse", "HEAD"])?;
+    let short_hash = git(None, &["rev-parse", "--short", "HEAD"])?;
+    let subject = git(None, &["log", "-1", "--format=%s", "HEAD"])?;
+    let files = git(None, &["show", "--format=", "--name-only", "HEAD"])?;
+    let stat = git(None, &["show", "--format=", "--shortstat", "HEAD"])?;
+    Some(Commit {
+        hash,
+        short_hash,
+        subject,
+        files: files.lines().map(String::from).collect(),
+        stat: stat.trim().to_string(),
+    })
+}
+
+/// Returns whether a rebase is under way, during which commits are being
+/// replayed rather than written
+pub fn rebasing() -> bool {
+    ["rebase-merge", "rebase-apply"].iter().any(|dir| {
+        git(None, &["rev-parse", "--git-path", dir]).is_some_and(|path| Path::new(&path).exists())
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A commit from the history of a repository, for `import --git`
+pub struct LogCommit {
+    /// The full hash, which identifies the commit across imports
+    pub hash: String,
+    /// The abbreviated hash
+    pub short_hash: String,
+    /// When the author wrote it, in local time
+    pub time: NaiveDateTime,
+    /// The first line of the message
+    pub subject: String,
+    /// The rest of the message
+    pub body: String,
+}
+
+/// Returns the commits in the history of `dir` by authors matching `author`,
+/// oldest first
+pub fn log(dir: &Path, author: &str) -> Option<Vec<LogCommit>> {
+    let author = format!("--author={author}");
+    let output = git(
+        Some(dir),
+        &[
+            "log",
+            "--reverse",
+            &author,
+            "--format=%H%x1f%h%x1f%aI%x1f%s%x1f%b%x1e",
+        ],
+    )?;
+    let commits = output
+        .split('\x1e')
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.trim_start_matches('\n').split('\x1f').collect();
+            let [hash, short_hash, time, subject, body] = fields[..] else {
+                return None;
+            };
+            let time = DateTime::parse_from_rfc3339(time).ok()?;
+            Some(LogCommit {
+                hash: hash.to_string(),
+                short_hash: short_hash.to_string(),
+                time: time.with_timezone(&Local).naive_local(),
+                subject: subject.to_string(),
+                body: body.trim().to_string(),
+            })
+        })
+        .collect();
+    Some(commits)
+}
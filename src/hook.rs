@@ -0,0 +1,199 @@
+//! Connects the diary to the code it documents: `code_diary hook install` adds
+//! a `post-commit` hook to the current repository that offers to record an
+//! entry about each commit.
+//!
+//! The hook is a block between marker lines, so it can live alongside
+//! whatever else the user's hook does and be taken out again by
+//! `code_diary hook uninstall`.
+
+use std::path::PathBuf;
+
+use crate::templates::EntryTemplate;
+use crate::{fail, git};
+
+const BEGIN: &str = "# >>> code_diary >>>";
+const END: &str = "# <<< code_diary <<<";
+/// Every entry recorded by the hook gets this tag
+pub const TAG: &str = "commit";
+
+/// Returns the path of the current repository's `post-commit` hook, honoring
+/// `core.hooksPath`
+/// # Panics
+/// Will 'gracefully' exit outside a git repository
+fn hook_path() -> PathBuf {
+    match git::git(None, &["rev-parse", "--git-path", "hooks/post-commit"]) {
+        Some(path) => PathBuf::from(path),
+        None => fail!("Not in a git repository"),
+    }
+}
+
+/// The lines added to the hook. Without `auto`, the hook reconnects to the
+/// terminal so it can ask questions, and stays quiet when there is none, e.g.
+/// when committing from an IDE.
+fn block(auto: bool) -> String {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe.display().to_string(),
+        Err(_) => String::from("code_diary"),
+    };
+    let exe = format!("'{}'", exe.replace('\'', "'\\''"));
+    let run = match auto {
+        true => format!("{exe} hook run --auto"),
+        false => format!("if (exec < /dev/tty) 2> /dev/null; then {exe} hook run < /dev/tty; fi"),
+    };
+    format!("{BEGIN}\n# Added by `code_diary hook install`, remove with `code_diary hook uninstall`\n{run}\n{END}\n")
+}
+
+/// Splits `script` around the code_diary block, if it has one
+fn split(script: &str) -> Option<(&str, &str)> {
+    let start = script.find(BEGIN)?;
+    let end = script[start..].find(END)? + start + END.len();
+    let after = script[end..].strip_prefix('\n').unwrap_or(&script[end..]);
+    Some((&script[..start], after))
+}
+
+/// Puts `block` into `script` in place of the code_diary block, or at the end
+/// if it has none
+fn splice(script: &str, block: &str) -> String {
+    match split(script) {
+        Some((before, after)) => format!("{before}{block}{after}"),
+        None if script.trim().is_empty() => format!("#!/bin/sh\n{block}"),
+        None => format!("{}\n\n{block}", script.trim_end()),
+    }
+}
+
+/// Takes the code_diary block out of `script`, returning what is left, or
+/// `None` if it has no block. Nothing is left if the block was all there was
+/// besides the shebang.
+fn unsplice(script: &str) -> Option<String> {
+    let (before, after) = split(script)?;
+    let rest = format!("{}\n{after}", before.trim_end());
+    Some(match rest.trim() {
+        "" | "#!/bin/sh" => String::new(),
+        _ => format!("{}\n", rest.trim_end()),
+    })
+}
+
+/// Adds the hook to the current repository, or replaces the block if it is
+/// already there. Returns the path of the hook.
+/// # Panics
+/// Will 'gracefully' exit outside a git repository or if the hook cannot be
+/// written
+pub fn install(auto: bool) -> PathBuf {
+    let path = hook_path();
+    let script = std::fs::read_to_string(&path).unwrap_or_default();
+    let script = splice(&script, &block(auto));
+
+    if let Some(dir) = path.parent() {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            let dir = dir.display();
+            fail!("Could not create {dir}: {e}");
+        }
+    }
+    if let Err(e) = std::fs::write(&path, script) {
+        let path = path.display();
+        fail!("Could not write {path}: {e}");
+    }
+    make_executable(&path);
+    path
+}
+
+#[cfg(unix)]
+fn make_executable(path: &std::path::Path) {
+    use std::os::unix::fs::PermissionsExt;
+    if let Err(e) = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)) {
+        let path = path.display();
+        fail!("Could not make {path} executable: {e}");
+    }
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &std::path::Path) {}
+
+/// Takes the hook out of the current repository, deleting the file if
+/// nothing else is left in it. Returns the path of the hook.
+/// # Panics
+/// Will 'gracefully' exit if the hook is not installed or cannot be changed
+pub fn uninstall() -> PathBuf {
+    let path = hook_path();
+    let script = std::fs::read_to_string(&path).unwrap_or_default();
+    let rest = match unsplice(&script) {
+        Some(rest) => rest,
+        None => fail!("The code_diary hook is not installed in this repository"),
+    };
+    let result = match rest.is_empty() {
+        true => std::fs::remove_file(&path),
+        false => std::fs::write(&path, rest),
+    };
+    if let Err(e) = result {
+        let path = path.display();
+        fail!("Could not change {path}: {e}");
+    }
+    path
+}
+
+/// The entry the hook offers to record for `commit`: the subject as title and
+/// the hash, branch, diffstat and changed files as content
+pub fn commit_template(commit: &git::Commit) -> EntryTemplate {
+    let branch = match git::branch() {
+        Some(branch) => format!(" on `{branch}`"),
+        None => String::new(),
+    };
+    let mut body = format!("Commit `{}`{branch}", commit.short_hash);
+    if !commit.stat.is_empty() {
+        body.push_str(&format!(": {}", commit.stat));
+    }
+    body.push('\n');
+    if !commit.files.is_empty() {
+        body.push('\n');
+        for file in &commit.files {
+            body.push_str(&format!("- `{file}`\n"));
+        }
+    }
+    EntryTemplate {
+        title: Some(commit.subject.clone()),
+        tags: vec![String::from(TAG)],
+        body,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER_HOOK: &str = "#!/bin/sh\nmake lint\n";
+
+    #[test]
+    fn splice_starts_a_script_or_adds_to_the_end() {
+        let block = block(false);
+        assert_eq!(splice("", &block), format!("#!/bin/sh\n{block}"));
+        assert_eq!(
+            splice(USER_HOOK, &block),
+            format!("#!/bin/sh\nmake lint\n\n{block}")
+        );
+    }
+
+    #[test]
+    fn splice_replaces_the_block_in_place() {
+        let script = format!("#!/bin/sh\nbefore\n{}after\n", block(false));
+        assert_eq!(
+            splice(&script, &block(true)),
+            format!("#!/bin/sh\nbefore\n{}after\n", block(true))
+        );
+    }
+
+    #[test]
+    fn unsplice_gives_back_the_users_script() {
+        let block = block(false);
+        assert_eq!(unsplice(&splice(USER_HOOK, &block)).unwrap(), USER_HOOK);
+        let script = format!("#!/bin/sh\nbefore\n{block}after\n");
+        assert_eq!(unsplice(&script).unwrap(), "#!/bin/sh\nbefore\nafter\n");
+    }
+
+    #[test]
+    fn unsplice_leaves_nothing_of_a_script_it_started() {
+        assert_eq!(unsplice(&splice("", &block(true))).unwrap(), "");
+        assert_eq!(unsplice(USER_HOOK), None);
+        // A block missing its end marker is not ours to remove
+        assert_eq!(unsplice(&format!("{BEGIN}\nrun\n")), None);
+    }
+}
@@ -0,0 +1,148 @@
+//! Backfills a diary from elsewhere, for `code_diary import`. For now that is
+//! the history of a git repository: each commit, or each day of commits,
+//! becomes an entry written at the time of the commit.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chrono::NaiveDate;
+
+use crate::backend::{current_notebook, get_imported_commits, get_latest_id};
+use crate::diaryentry::DiaryEntry;
+use crate::git::{self, LogCommit};
+use crate::selection::Selection;
+use crate::timestamps::TimeStamp;
+use crate::{fail, hook};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How commits are turned into entries
+pub enum Grouping {
+    /// An entry per commit
+    Commit,
+    /// An entry per day with a line per commit
+    Day,
+}
+
+impl Grouping {
+    /// Parses `commit` or `day`
+    /// # Panics
+    /// Will 'gracefully' exit if `name` is neither
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "commit" => Self::Commit,
+            "day" => Self::Day,
+            _ => fail!("Cannot group commits by '{name}', expected commit or day"),
+        }
+    }
+}
+
+/// Reads the commits by `author` in the repository at `dir` within the dates
+/// of `selection`, leaving out those imported before, and makes entries of
+/// them. Entries are numbered from the next free id but not stored; each
+/// comes with the full hashes of its commits.
+/// # Panics
+/// Will 'gracefully' exit if `dir` is not a git repository
+pub fn entries_from_git(
+    dir: &Path,
+    author: Option<&str>,
+    selection: &Selection,
+    grouping: Grouping,
+) -> Vec<(DiaryEntry, Vec<String>)> {
+    let root = match git::git(Some(dir), &["rev-parse", "--show-toplevel"]) {
+        Some(root) => root,
+        None => {
+            let dir = dir.display();
+            fail!("{dir} is not a git repository")
+        }
+    };
+    let repo = Path::new(&root)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned());
+    // Without an author, import the commits of whoever is configured there
+    let author = match author {
+        Some(author) => author.to_string(),
+        None => match git::git(Some(dir), &["config", "user.email"]) {
+            Some(email) => email,
+            None => fail!("Could not tell who you are in {root}; pass --author"),
+        },
+    };
+
+    let imported = get_imported_commits();
+    let commits: Vec<LogCommit> = git::log(dir, &author)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|commit| !imported.contains(&commit.hash))
+        .filter(|commit| selection.contains_date(&TimeStamp::from_datetime(commit.time)))
+        .collect();
+
+    let groups: Vec<Vec<LogCommit>> = match grouping {
+        Grouping::Commit => commits.into_iter().map(|commit| vec![commit]).collect(),
+        Grouping::Day => {
+            let mut days: BTreeMap<NaiveDate, Vec<LogCommit>> = BTreeMap::new();
+            for commit in commits {
+                days.entry(commit.time.date()).or_default().push(commit);
+            }
+            days.into_values().collect()
+        }
+    };
+
+    let first_id = get_latest_id() + 1;
+    groups
+        .into_iter()
+        .enumerate()
+        .map(|(index, commits)| {
+            let entry = entry_for(first_id + index as u64, &commits, repo.clone());
+            let hashes = commits.into_iter().map(|commit| commit.hash).collect();
+            (entry, hashes)
+        })
+        .collect()
+}
+
+/// Makes an entry of `commits`, which are in order and not empty. A single
+/// commit keeps its message; several are listed by subject under the day.
+fn entry_for(id: u64, commits: &[LogCommit], repo: Option<String>) -> DiaryEntry {
+    let last = &commits[commits.len() - 1];
+    let (title, content) = match commits {
+        [commit] => {
+            let mut content = format!("Commit `{}`", commit.short_hash);
+            if !commit.body.is_empty() {
+                content = format!("{}\n\n{content}", commit.body);
+            }
+            (commit.subject.clone(), content)
+        }
+        commits => {
+            let title = format!(
+                "{} commits on {}",
+                commits.len(),
+                last.time.format("%Y-%m-%d")
+            );
+            let lines: Vec<String> = commits
+                .iter()
+                .map(|commit| {
+                    format!(
+                        "- {} {} (`{}`)",
+                        commit.time.format("%H:%M"),
+                        commit.subject,
+                        commit.short_hash
+                    )
+                })
+                .collect();
+            (title, lines.join("\n"))
+        }
+    };
+    DiaryEntry {
+        id,
+        date: TimeStamp::from_datetime(last.time),
+        title: Some(title),
+        content,
+        notebook: current_notebook(),
+        tags: vec![String::from(hook::TAG)],
+        repo,
+        refs: Vec::new(),
+        attachments: Vec::new(),
+        ended: None,
+        fields: Vec::new(),
+        pinned: false,
+        starred: false,
+    }
+}
@@ -0,0 +1,265 @@
+//! Links between entries, for following up on earlier ones: `#42` in the
+//! content links to the entry with id 42 and `[[Some title]]` to the entry
+//! with that title.
+//!
+//! Links are found when an entry is added or edited and kept in the
+//! `EntryLinks` table, so backlinks can be shown without reading every entry.
+//! Entries mentioning the one stored are relinked with it, so a link can be
+//! written before the entry it points to and follows a retitled entry.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::diaryentry::DiaryEntry;
+use crate::theme::{paint, Role};
+
+/// The id, title and notebook of an entry, which is all that is needed to
+/// resolve links to it
+pub type Target = (u64, String, String);
+
+/// Matches `#42` links, compiled on first use
+static ID_LINKS: OnceLock<Regex> = OnceLock::new();
+/// Matches `[[Some title]]` links, compiled on first use
+static TITLE_LINKS: OnceLock<Regex> = OnceLock::new();
+
+/// Returns the target `entry` makes for links to it
+pub fn target(entry: &DiaryEntry) -> Target {
+    (
+        entry.id,
+        entry.title.clone().unwrap_or_default(),
+        entry.notebook.clone(),
+    )
+}
+
+/// Returns the ids of the entries `entry` links to, in the order the links
+/// appear. `#` links to ids that do not exist, titles that match no entry and
+/// links to the entry itself are left out. A title shared by several entries
+/// means the newest one, preferring the notebook `entry` is in.
+pub fn targets(entry: &DiaryEntry, entries: &[Target]) -> Vec<u64> {
+    let ids = ID_LINKS.get_or_init(|| Regex::new(r"(?:^|[^\w#&/])#(\d+)\b").unwrap());
+    let titles = TITLE_LINKS.get_or_init(|| Regex::new(r"\[\[([^\[\]\n]+)\]\]").unwrap());
+
+    let mut found: Vec<(usize, u64)> = ids
+        .captures_iter(&entry.content)
+        .filter_map(|captures| {
+            let id = captures[1].parse::<u64>().ok()?;
+            entries
+                .iter()
+                .any(|(target, _, _)| *target == id)
+                .then(|| (captures.get(1).unwrap().start(), id))
+        })
+        .collect();
+    found.extend(titles.captures_iter(&entry.content).filter_map(|captures| {
+        let title = captures[1].trim();
+        entries
+            .iter()
+            .filter(|(_, target, _)| target.eq_ignore_ascii_case(title))
+            .max_by_key(|(id, _, notebook)| (*notebook == entry.notebook, *id))
+            .map(|(id, _, _)| (captures.get(0).unwrap().start(), *id))
+    }));
+    found.sort();
+
+    let mut seen = HashSet::from([entry.id]);
+    found
+        .into_iter()
+        .map(|(_, id)| id)
+        .filter(|id| seen.insert(*id))
+        .collect()
+}
+
+/// Returns the links that storing `entry` may change, as the id of each entry
+/// linking and the ids it now links to: those from `entry` itself, from the
+/// entries mentioning its id or title, which may have been written before it,
+/// and from the entries linking to it, which may have used its old title.
+/// `entries` holds every entry, `entry` as stored among them, and `links`
+/// the links before it was stored.
+pub fn relink(
+    entry: &DiaryEntry,
+    entries: &[DiaryEntry],
+    links: &[(u64, u64)],
+) -> Vec<(u64, Vec<u64>)> {
+    let all: Vec<Target> = entries.iter().map(target).collect();
+    let own = [target(entry)];
+    entries
+        .iter()
+        .filter(|source| {
+            source.id == entry.id
+                || links.contains(&(source.id, entry.id))
+                || !targets(source, &own).is_empty()
+        })
+        .map(|source| (source.id, targets(source, &all)))
+        .collect()
+}
+
+/// Lists the entries `entry` links to and the entries linking to it, for
+/// `view`. Returns an empty string if there are none.
+pub fn show(entry: &DiaryEntry, links: &[(u64, u64)], entries: &[DiaryEntry]) -> String {
+    let by_id: HashMap<u64, &DiaryEntry> = entries.iter().map(|entry| (entry.id, entry)).collect();
+    let section = |heading: &str, ids: Vec<u64>| -> String {
+        let lines: String = ids
+            .iter()
+            .filter_map(|id| by_id.get(id))
+            .map(|entry| format!("\n  {}", entry.show_summary().trim_start()))
+            .collect();
+        match lines.is_empty() {
+            true => lines,
+            false => format!("\n\n{}{lines}", paint(Role::Muted, heading)),
+        }
+    };
+    let outgoing = links
+        .iter()
+        .filter(|(source, _)| *source == entry.id)
+        .map(|(_, target)| *target)
+        .collect();
+    let incoming = links
+        .iter()
+        .filter(|(_, target)| *target == entry.id)
+        .map(|(source, _)| *source)
+        .collect();
+    format!(
+        "{}{}",
+        section("Links:", outgoing),
+        section("Linked from:", incoming)
+    )
+}
+
+/// Draws the entries within `depth` links of `entry` as a tree, following
+/// links both ways: `→` for a link from the entry above and `←` for a link
+/// to it. Entries already drawn are not followed again.
+pub fn graph(
+    entry: &DiaryEntry,
+    links: &[(u64, u64)],
+    entries: &[DiaryEntry],
+    depth: usize,
+) -> String {
+    let by_id: HashMap<u64, &DiaryEntry> = entries.iter().map(|entry| (entry.id, entry)).collect();
+    let mut lines = vec![entry.show_summary().trim_start().to_string()];
+    let mut seen = HashSet::from([entry.id]);
+    branch(entry.id, links, &by_id, depth, "", &mut seen, &mut lines);
+    lines.join("\n")
+}
+
+fn branch(
+    id: u64,
+    links: &[(u64, u64)],
+    by_id: &HashMap<u64, &DiaryEntry>,
+    depth: usize,
+    indent: &str,
+    seen: &mut HashSet<u64>,
+    lines: &mut Vec<String>,
+) {
+    if depth == 0 {
+        return;
+    }
+    let neighbours: Vec<(&str, u64)> = links
+        .iter()
+        .filter_map(|&(source, target)| match (source == id, target == id) {
+            (true, _) => Some(("→", target)),
+            (_, true) => Some(("←", source)),
+            _ => None,
+        })
+        .filter(|(_, other)| by_id.contains_key(other) && seen.insert(*other))
+        .collect();
+
+    for (index, (arrow, other)) in neighbours.iter().enumerate() {
+        let last = index + 1 == neighbours.len();
+        let (fork, rest) = match last {
+            true => ("└─", "    "),
+            false => ("├─", "│   "),
+        };
+        lines.push(format!(
+            "{indent}{}{} {}",
+            paint(Role::Muted, fork),
+            paint(Role::Accent, arrow),
+            by_id[other].show_summary().trim_start()
+        ));
+        let indent = format!("{indent}{}", paint(Role::Muted, rest));
+        branch(*other, links, by_id, depth - 1, &indent, seen, lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(id: u64, title: &str, notebook: &str) -> Target {
+        (id, title.to_string(), notebook.to_string())
+    }
+
+    #[test]
+    fn id_links_need_an_existing_entry_and_a_word_boundary() {
+        let entries = [target(1, "One", "work"), target(2, "Two", "work")];
+        let entry = DiaryEntry {
+            id: 9,
+            content: String::from("See #2, then #1. Not #3, x#1, &#1, path/#1, ##1 or #1x."),
+            ..Default::default()
+        };
+        assert_eq!(targets(&entry, &entries), vec![2, 1]);
+    }
+
+    #[test]
+    fn titles_prefer_the_same_notebook_then_the_newest() {
+        let entries = [
+            target(1, "Standup", "work"),
+            target(2, "Standup", "home"),
+            target(3, "standup", "work"),
+            target(4, "Retro", "home"),
+        ];
+        let mut entry = DiaryEntry {
+            id: 9,
+            notebook: String::from("work"),
+            content: String::from("[[ standup ]] and [[Retro]] but not [[Planning]]"),
+            ..Default::default()
+        };
+        assert_eq!(targets(&entry, &entries), vec![3, 4]);
+        entry.notebook = String::from("home");
+        assert_eq!(targets(&entry, &entries), vec![2, 4]);
+    }
+
+    #[test]
+    fn links_keep_their_order_without_repeats_or_self_links() {
+        let entries = [
+            target(1, "One", "work"),
+            target(2, "Two", "work"),
+            target(9, "Nine", "work"),
+        ];
+        let entry = DiaryEntry {
+            id: 9,
+            content: String::from("[[Two]] then #1, #2 again, and myself #9"),
+            ..Default::default()
+        };
+        assert_eq!(targets(&entry, &entries), vec![2, 1]);
+    }
+
+    #[test]
+    fn links_written_before_their_target_or_to_an_old_title_are_relinked() {
+        let entry = |id: u64, title: &str, content: &str| DiaryEntry {
+            id,
+            title: Some(title.to_string()),
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let mut entries = vec![
+            entry(1, "One", "Follow up in #3 and [[Three]]"),
+            entry(2, "Two", "[[Old]] and [[One]]"),
+            entry(3, "Three", "Back to #1"),
+        ];
+        assert_eq!(
+            relink(&entries[2], &entries, &[]),
+            vec![(1, vec![3]), (3, vec![1])]
+        );
+
+        entries[2].title = Some(String::from("Old"));
+        assert_eq!(
+            relink(&entries[2], &entries, &[(1, 3), (3, 1)]),
+            vec![(1, vec![3]), (2, vec![3, 1]), (3, vec![1])]
+        );
+        entries[2].title = Some(String::from("New"));
+        assert_eq!(
+            relink(&entries[2], &entries, &[(1, 3), (2, 3), (2, 1), (3, 1)]),
+            vec![(1, vec![3]), (2, vec![1]), (3, vec![1])]
+        );
+    }
+}
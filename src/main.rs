@@ -2,16 +2,50 @@
 #![warn(missing_docs)]
 #![doc = include_str!("../README.md")]
 
+pub mod attachments;
 pub mod backend;
+pub mod backup;
+pub mod calendar;
+pub mod completions;
+pub mod config;
+pub mod crypto;
 pub mod diaryentry;
+pub mod doctor;
+pub mod fields;
 pub mod frontend;
+pub mod git;
+pub mod hook;
+pub mod import;
+pub mod links;
+pub mod markdown;
+pub mod pager;
+pub mod refs;
+pub mod report;
+pub mod selection;
+pub mod sessions;
+pub mod stats;
+pub mod templates;
+pub mod theme;
 pub mod timestamps;
+pub mod tui;
 
 use argh::FromArgs;
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// Interact with diary entries
 struct Cli {
+    /// the notebook to use (defaults to the configured default_notebook)
+    #[argh(option, short = 'n')]
+    notebook: Option<String>,
+
+    /// print long output directly instead of through $PAGER
+    #[argh(switch)]
+    no_pager: bool,
+
+    /// when to use colors: auto (the default), always or never
+    #[argh(option)]
+    color: Option<String>,
+
     #[argh(subcommand)]
     action: Option<Action>,
 }
@@ -22,8 +56,58 @@ struct Cli {
 pub enum Action {
     /// Add an entry
     Add(Add),
+    /// Read the files kept with entries
+    Attachment(AttachmentCommand),
+    /// Back up the diary
+    Backup(Backup),
+    /// Show a month of entries
+    Calendar(Calendar),
+    /// Print a shell completion script
+    Completions(Completions),
+    /// Manage the configuration file
+    Config(ConfigCommand),
     /// Delete an entry
     Delete(Delete),
+    /// Check the database for problems
+    Doctor(Doctor),
+    /// Edit an entry
+    Edit(Edit),
+    /// Record entries about commits from a git hook
+    Hook(HookCommand),
+    /// Create entries from elsewhere
+    Import(Import),
+    /// Set up the diary
+    Init(Init),
+    /// Show the entries linked to an entry
+    Links(Links),
+    /// List entries
+    List(List),
+    /// Manage notebooks
+    Notebook(Notebook),
+    /// Keep an entry at the top of the list
+    Pin(Pin),
+    /// Summarize recent entries
+    Report(Report),
+    /// Replace the diary with a backup
+    Restore(Restore),
+    /// Print the code an entry refers to
+    ShowRef(ShowRef),
+    /// Start a work session
+    Start(Start),
+    /// Mark an entry as important
+    Star(Star),
+    /// Show statistics about entries
+    Stats(Stats),
+    /// Stop the work session and write an entry about it
+    Stop(Stop),
+    /// Add or remove tags on entries
+    Tag(Tag),
+    /// Manage entry templates
+    Template(TemplateCommand),
+    /// Add to today's running log
+    Today(Today),
+    /// Browse entries in a full-screen interface
+    Tui(Tui),
     /// View an entry
     View(View),
 }
@@ -39,108 +123,1491 @@ pub struct Add {
     /// the content (if not provided, code_diary will start interactively)
     #[argh(option, short = 'c')]
     content: Option<String>,
+
+    /// a tag to label the entry with (can be repeated)
+    #[argh(option)]
+    tag: Vec<String>,
+
+    /// start from the named template, e.g. bug or retro
+    #[argh(option)]
+    template: Option<String>,
+
+    /// a file or lines the entry is about, e.g. src/parser.rs:120-140 (can
+    /// be repeated)
+    #[argh(option, long = "ref")]
+    refs: Vec<String>,
+
+    /// a file to keep with the entry, e.g. a screenshot or log (can be
+    /// repeated)
+    #[argh(option)]
+    attach: Vec<String>,
+
+    /// a rating for one of the configured fields, e.g. mood=4 (can be
+    /// repeated)
+    #[argh(option)]
+    field: Vec<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "backup")]
+/// Copy the diary into the backups directory, removing the oldest backups.
+/// Automatic backups, taken before destructive commands, are kept apart in
+/// its `automatic` directory.
+pub struct Backup {
+    /// how many backups to keep (defaults to the configured backup_keep)
+    #[argh(option)]
+    keep: Option<usize>,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "attachment")]
+/// Read the files kept with entries by `add --attach`
+pub struct AttachmentCommand {
+    #[argh(subcommand)]
+    action: AttachmentAction,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand)]
+/// The different actions that can be taken on attachments
+pub enum AttachmentAction {
+    /// Extract an attachment
+    Get(AttachmentGet),
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "get")]
+/// Write a file attached to an entry to disk
+pub struct AttachmentGet {
+    #[argh(positional)]
+    /// the id of the entry
+    id: u64,
+
+    #[argh(positional)]
+    /// the name of the attachment, as listed by `view`
+    name: String,
+
+    /// where to write it, or - for standard output (defaults to its name in
+    /// the current directory)
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "calendar")]
+/// Show a month with the days that have entries, and list a day's entries
+pub struct Calendar {
+    #[argh(positional)]
+    /// the month to show (YYYY-MM, defaults to this month)
+    month: Option<String>,
+
+    /// list the entries of this day of the month, or of a YYYY-MM-DD date
+    #[argh(option, short = 'd')]
+    day: Option<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "completions")]
+/// Print a completion script for bash, zsh or fish, e.g.
+/// `source <(code_diary completions bash)`
+pub struct Completions {
+    #[argh(positional)]
+    /// the shell: bash, zsh or fish
+    shell: Option<String>,
+
+    /// print the entry ids, tags, notebooks or settings to complete (used by
+    /// the scripts)
+    #[argh(option, hidden_help)]
+    candidates: Option<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "config")]
+/// Manage the configuration file
+pub struct ConfigCommand {
+    #[argh(subcommand)]
+    action: ConfigAction,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand)]
+/// The different actions that can be taken on the configuration
+pub enum ConfigAction {
+    /// Print a setting
+    Get(ConfigGet),
+    /// Change a setting
+    Set(ConfigSet),
+    /// Print every setting
+    List(ConfigList),
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "get")]
+/// Print a setting
+pub struct ConfigGet {
+    #[argh(positional)]
+    /// the name of the setting
+    key: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "set")]
+/// Change a setting
+pub struct ConfigSet {
+    #[argh(positional)]
+    /// the name of the setting
+    key: String,
+
+    #[argh(positional)]
+    /// the new value
+    value: String,
 }
 
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "links")]
+/// Show the entries linked to and from an entry with `#42` or `[[title]]`
+pub struct Links {
+    #[argh(positional)]
+    /// the id of the entry
+    id: u64,
+
+    /// how many links away to follow (defaults to 1)
+    #[argh(option, short = 'd', default = "1")]
+    depth: usize,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "list")]
+/// Print every setting
+pub struct ConfigList {}
+
 #[derive(FromArgs, PartialEq, Debug, Clone)]
 #[argh(subcommand, name = "delete")]
-/// Delete at a given index
+/// Delete entries by id, range of ids (e.g. 10-14) or filter. Ids are the
+/// ones shown by `list`, not positions in the list.
 pub struct Delete {
     #[argh(positional)]
-    /// the index to delete at
-    index: Option<u64>,
+    /// the ids or ranges of ids to delete, as shown by `list`
+    ids: Vec<String>,
+
+    /// only delete entries with this tag
+    #[argh(option)]
+    tag: Option<String>,
+
+    /// only delete entries written before this date (YYYY-MM-DD, today,
+    /// yesterday, 3d or 2w)
+    #[argh(option)]
+    before: Option<String>,
+
+    /// only delete entries written on or after this date (YYYY-MM-DD, today,
+    /// yesterday, 3d or 2w)
+    #[argh(option)]
+    since: Option<String>,
+
+    /// do not ask for confirmation
+    #[argh(switch, short = 'y')]
+    yes: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "doctor")]
+/// Check the database for damage, unreadable entries, shared ids and rows
+/// left behind by deleted entries
+pub struct Doctor {
+    /// repair what can be repaired, quarantining unreadable entries. The
+    /// diary is backed up first.
+    #[argh(switch)]
+    fix: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "edit")]
+/// Edit an entry, in an editor unless a new title or content is given
+pub struct Edit {
+    #[argh(positional)]
+    /// the id of the entry to edit, as shown by `list`
+    id: Option<u64>,
+
+    /// the new title
+    #[argh(option, short = 't')]
+    title: Option<String>,
+
+    /// the new content
+    #[argh(option, short = 'c')]
+    content: Option<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "hook")]
+/// Manage the git hook that records entries about commits
+pub struct HookCommand {
+    #[argh(subcommand)]
+    action: HookAction,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand)]
+/// The different actions that can be taken on the git hook
+pub enum HookAction {
+    /// Install the hook
+    Install(HookInstall),
+    /// Uninstall the hook
+    Uninstall(HookUninstall),
+    /// Record an entry about the latest commit
+    Run(HookRun),
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "install")]
+/// Add a post-commit hook to the current repository that offers to record an
+/// entry about each commit
+pub struct HookInstall {
+    /// record every commit without asking
+    #[argh(switch)]
+    auto: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "uninstall")]
+/// Remove the post-commit hook from the current repository
+pub struct HookUninstall {}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "run")]
+/// Record an entry about the latest commit; this is what the hook runs
+pub struct HookRun {
+    /// record the entry without asking
+    #[argh(switch)]
+    auto: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "import")]
+/// Create entries from the commits in a git repository, written at the time
+/// of each commit. Commits imported before are skipped.
+pub struct Import {
+    /// the repository to import the history of
+    #[argh(option)]
+    git: String,
+
+    /// only commits by this author, as understood by `git log --author`
+    /// (defaults to the repository's user.email)
+    #[argh(option)]
+    author: Option<String>,
+
+    /// only commits from this date on (YYYY-MM-DD, today, yesterday, 3d or 2w)
+    #[argh(option)]
+    since: Option<String>,
+
+    /// only commits from before this date (YYYY-MM-DD, today, yesterday, 3d or 2w)
+    #[argh(option)]
+    before: Option<String>,
+
+    /// make an entry per commit or per day (commit or day, defaults to commit)
+    #[argh(option, default = "String::from(\"commit\")")]
+    group: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "init")]
+/// Set up the diary, optionally encrypting it
+pub struct Init {
+    /// encrypt entry titles and contents with a passphrase
+    #[argh(switch)]
+    encrypt: bool,
+
+    /// keep the unlocked key in the session keyring (only with --encrypt)
+    #[argh(switch)]
+    cache_key: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "list")]
+/// List the newest entries
+pub struct List {
+    /// how many entries to show (defaults to the configured list_limit)
+    #[argh(option, short = 'l')]
+    limit: Option<u64>,
+
+    /// show every entry
+    #[argh(switch, short = 'a')]
+    all: bool,
+
+    /// only show starred entries
+    #[argh(switch)]
+    starred: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "notebook")]
+/// Manage notebooks
+pub struct Notebook {
+    #[argh(subcommand)]
+    action: NotebookAction,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand)]
+/// The different actions that can be taken on notebooks
+pub enum NotebookAction {
+    /// Create a notebook
+    New(NotebookNew),
+    /// List notebooks
+    List(NotebookList),
+    /// Move an entry to another notebook
+    Move(NotebookMove),
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "new")]
+/// Create a notebook
+pub struct NotebookNew {
+    #[argh(positional)]
+    /// the name of the notebook
+    name: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "list")]
+/// List notebooks and how many entries they hold
+pub struct NotebookList {}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "move")]
+/// Move an entry to another notebook
+pub struct NotebookMove {
+    #[argh(positional)]
+    /// the id of the entry
+    id: u64,
+
+    #[argh(positional)]
+    /// the notebook to move it to
+    notebook: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "pin")]
+/// Pin an entry so it stays at the top of `list` and the entry picker
+pub struct Pin {
+    #[argh(positional)]
+    /// the id of the entry
+    id: u64,
+
+    /// unpin the entry instead
+    #[argh(switch)]
+    undo: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "report")]
+/// Summarize recent entries as Markdown or text, grouped by day, repo or tag
+pub struct Report {
+    /// only entries written on or after this date (YYYY-MM-DD, today,
+    /// yesterday, 3d or 2w; defaults to today)
+    #[argh(option)]
+    since: Option<String>,
+
+    /// only entries written before this date
+    #[argh(option)]
+    before: Option<String>,
+
+    /// entries written this week, since Monday
+    #[argh(switch)]
+    week: bool,
+
+    /// only entries with this tag
+    #[argh(option)]
+    tag: Option<String>,
+
+    /// group entries by day, repo or tag (defaults to day)
+    #[argh(option)]
+    by: Option<String>,
+
+    /// the template: markdown, text or one of your own in the reports
+    /// directory (defaults to markdown)
+    #[argh(option, short = 'f')]
+    format: Option<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "restore")]
+/// Replace the diary with a backup after checking it is intact. The diary is
+/// backed up first.
+pub struct Restore {
+    #[argh(positional)]
+    /// the backup to restore
+    file: String,
+
+    /// do not ask for confirmation
+    #[argh(switch, short = 'y')]
+    yes: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "show-ref")]
+/// Print the lines an entry refers to as they were when it was written
+pub struct ShowRef {
+    #[argh(positional)]
+    /// the id of the entry
+    id: u64,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "start")]
+/// Start timing a work session; `stop` ends it and records an entry
+pub struct Start {
+    #[argh(positional)]
+    /// what you are working on, which becomes the title of the entry
+    title: Vec<String>,
+
+    /// a tag to label the entry with (can be repeated)
+    #[argh(option)]
+    tag: Vec<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "star")]
+/// Star an entry to find it again with `list --starred`
+pub struct Star {
+    #[argh(positional)]
+    /// the id of the entry
+    id: u64,
+
+    /// unstar the entry instead
+    #[argh(switch)]
+    undo: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "stats")]
+/// Show how much and how regularly you write, with a heatmap of the past year
+pub struct Stats {
+    /// chart one of the configured ratings over time instead, e.g. mood
+    #[argh(option)]
+    field: Option<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "stop")]
+/// Stop the work session and write an entry about it, with how long it took
+pub struct Stop {
+    /// notes on the session (if not provided, code_diary will ask)
+    #[argh(option, short = 'c')]
+    content: Option<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "tag")]
+/// Add or remove tags on entries chosen by id, range of ids or filter
+pub struct Tag {
+    #[argh(positional)]
+    /// the ids or ranges of ids to retag
+    ids: Vec<String>,
+
+    /// a tag to add (can be repeated)
+    #[argh(option)]
+    add: Vec<String>,
+
+    /// a tag to remove (can be repeated)
+    #[argh(option)]
+    remove: Vec<String>,
+
+    /// only retag entries with this tag
+    #[argh(option)]
+    tag: Option<String>,
+
+    /// only retag entries written before this date (YYYY-MM-DD, today,
+    /// yesterday, 3d or 2w)
+    #[argh(option)]
+    before: Option<String>,
+
+    /// only retag entries written on or after this date (YYYY-MM-DD, today,
+    /// yesterday, 3d or 2w)
+    #[argh(option)]
+    since: Option<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "template")]
+/// Manage the templates used by `add --template`
+pub struct TemplateCommand {
+    #[argh(subcommand)]
+    action: TemplateAction,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand)]
+/// The different actions that can be taken on templates
+pub enum TemplateAction {
+    /// List templates
+    List(TemplateList),
+    /// Create a template
+    New(TemplateNew),
+    /// Edit a template
+    Edit(TemplateEdit),
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "list")]
+/// List templates
+pub struct TemplateList {}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "new")]
+/// Create a template and open it in the editor
+pub struct TemplateNew {
+    #[argh(positional)]
+    /// the name of the template
+    name: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "edit")]
+/// Open a template in the editor
+pub struct TemplateEdit {
+    #[argh(positional)]
+    /// the name of the template
+    name: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "today")]
+/// Append a timestamped line to today's log entry, creating it if needed. With
+/// no text, the whole entry is opened in the editor.
+pub struct Today {
+    #[argh(positional, greedy)]
+    /// the line to append
+    text: Vec<String>,
 }
 
+#[derive(FromArgs, PartialEq, Debug, Clone)]
+#[argh(subcommand, name = "tui")]
+/// Browse, search, add, edit and delete entries in a full-screen interface
+pub struct Tui {}
+
 #[derive(FromArgs, PartialEq, Debug, Clone)]
 #[argh(subcommand, name = "view")]
-/// View entries
+/// View an entry by its id. Ids are the ones shown by `list`, not positions
+/// in the list.
 pub struct View {
     #[argh(positional)]
-    /// the index to view
-    index: Option<u64>,
+    /// the id of the entry to view, as shown by `list`
+    id: Option<u64>,
+
+    /// print the content as written instead of rendering its Markdown
+    #[argh(switch)]
+    raw: bool,
 }
 
 use backend::*;
+use config::Config;
 use diaryentry::DiaryEntry;
 use frontend::*;
+use selection::Selection;
 
-fn add(options: Add) {
+/// The tag that marks the running log kept by `today`, one entry per day
+const TODAY_TAG: &str = "daily";
+
+use chrono::Datelike;
+use std::io::IsTerminal;
+
+fn add(options: Add, config: &Config) {
     let added_from_args: bool = match &argh::from_env::<Cli>().action {
-        Some(Action::Add(Add {
-            title: _title,
-            content,
-        })) => content.is_some(),
+        Some(Action::Add(Add { content, .. })) => content.is_some(),
         _ => false,
     };
 
-    let Add { title, content } = options;
+    let Add {
+        title,
+        content,
+        tag,
+        template,
+        refs,
+        attach,
+        field,
+    } = options;
+
+    let fields: Vec<(String, u8)> = field
+        .iter()
+        .map(|arg| fields::parse(arg, &config.fields()))
+        .collect();
 
+    // Check the files before asking for anything, so nothing typed is lost
+    let refs: Vec<refs::FileRef> = refs.iter().map(|arg| refs::FileRef::resolve(arg)).collect();
+    let attachments: Vec<(attachments::Attachment, Vec<u8>)> = attach
+        .iter()
+        .map(|path| attachments::Attachment::read(path, config.max_attachment_mb()))
+        .collect();
+    attachments::check_names(&attachments);
+
+    let template = match template {
+        Some(name) => templates::EntryTemplate::load(&name),
+        None => templates::EntryTemplate::default(),
+    };
+    let mut entry = compose_entry(
+        config,
+        title,
+        content,
+        tag,
+        fields,
+        template,
+        added_from_args,
+    );
+    entry.refs = refs;
+    for (attachment, data) in attachments {
+        store_blob(&attachment.hash, &data);
+        entry.attachments.push(attachment);
+    }
+    store_entry(&entry);
+}
+
+/// Asks for whatever of the title, content and ratings was not given,
+/// starting from `template`, and returns the entry without storing it
+fn compose_entry(
+    config: &Config,
+    title: Option<String>,
+    content: Option<String>,
+    tags: Vec<String>,
+    mut fields: Vec<(String, u8)>,
+    template: templates::EntryTemplate,
+    added_from_args: bool,
+) -> DiaryEntry {
     let title: Option<String> = match added_from_args {
-        true => title.to_owned(),
+        true => title.or(template.title),
         false => match title {
             Some(title) => Some(title.to_owned()),
-            None => prompt_for_title(),
+            None if config.prompt_title() => {
+                prompt_for_title_or(template.title.as_deref().unwrap_or(""))
+            }
+            None => template.title,
         },
     };
 
-    let content: String = match content {
-        Some(content) => content.to_owned(),
-        None => prompt_for_content(),
+    let content: String = match (content, &config.editor) {
+        (Some(content), _) => content.to_owned(),
+        (None, Some(editor)) => prompt_for_content_in_editor(editor, &template.body),
+        // A template with several lines cannot be filled in on one line
+        (None, None) if template.body.contains('\n') => {
+            prompt_for_content_in_editor(&config.editor_or_default(), &template.body)
+        }
+        (None, None) => prompt_for_content_or(&template.body),
     };
 
-    let entry = DiaryEntry::from_str_and_option(&title, &content);
-    store_entry(&entry);
+    if !added_from_args {
+        let missing: Vec<String> = config
+            .fields()
+            .into_iter()
+            .filter(|name| !fields.iter().any(|(given, _)| given == name))
+            .collect();
+        fields.extend(prompt_for_fields(&missing));
+    }
+    fields::sort(&mut fields, &config.fields());
+
+    let mut entry = DiaryEntry::from_str_and_option(&title, &content);
+    entry.tags = tags;
+    entry.fields = fields;
+    for tag in template.tags {
+        if !entry.tags.contains(&tag) {
+            entry.tags.push(tag);
+        }
+    }
+    entry
+}
+
+fn attachment(options: AttachmentCommand) {
+    let AttachmentAction::Get(AttachmentGet { id, name, output }) = options.action;
+
+    let entry = match get_entries().into_iter().find(|entry| entry.id == id) {
+        Some(entry) => entry,
+        None => fail!("There is no entry with id {id}"),
+    };
+    let attachment = match entry.attachments.iter().find(|a| a.name == name) {
+        Some(attachment) => attachment,
+        None => fail!("Entry {id} has no attachment called {name}"),
+    };
+    let data = match get_blob(&attachment.hash) {
+        Some(data) => data,
+        None => fail!("The contents of {name} are missing from the database"),
+    };
+
+    let output = output.unwrap_or(name);
+    if output == "-" {
+        use std::io::Write;
+        if let Err(e) = std::io::stdout().write_all(&data) {
+            fail!("Could not write {output}: {e}");
+        }
+        return;
+    }
+    if std::path::Path::new(&output).exists() {
+        fail!("{output} already exists; choose another path with --output");
+    }
+    if let Err(e) = std::fs::write(&output, &data) {
+        fail!("Could not write {output}: {e}");
+    }
+    let size = attachments::show_size(attachment.size);
+    println!("Saved {output} ({size}).");
+}
+
+fn backup(options: Backup, config: &Config) {
+    let keep = options.keep.unwrap_or(config.backup_keep());
+    if keep == 0 {
+        fail!("--keep must be above 0");
+    }
+    let path = backup::create(&config.backup_dir(), keep);
+    println!("Backed up the diary to {}.", path.display());
+}
+
+fn restore(options: Restore, config: &Config) {
+    let Restore { file, yes } = options;
+    let path = std::path::Path::new(&file);
+
+    backup::check(path);
+    if !yes && !prompt_to_restore(&file) {
+        std::process::exit(0);
+    }
+    report_backup(backup::restore(path, config));
+    println!("Restored {file}.");
+}
+
+/// Tells the user where the diary was backed up before a destructive command
+fn report_backup(path: Option<std::path::PathBuf>) {
+    if let Some(path) = path {
+        let note = format!("Backed up the diary to {} first.", path.display());
+        println!("{}", theme::paint(theme::Role::Muted, &note));
+    }
+}
+
+fn manage_config(options: ConfigCommand, mut config: Config) {
+    match options.action {
+        ConfigAction::Get(ConfigGet { key }) => match config.get(&key) {
+            Some(value) => println!("{value}"),
+            None => std::process::exit(1),
+        },
+        ConfigAction::Set(ConfigSet { key, value }) => {
+            config.set(&key, &value);
+            config.save();
+        }
+        ConfigAction::List(ConfigList {}) => {
+            for key in config::KEYS {
+                match config.get(key) {
+                    Some(value) => println!("{key} = {value}"),
+                    None => println!("{key} ="),
+                }
+            }
+        }
+    }
+}
+
+fn delete(options: Delete, config: &Config) {
+    let Delete {
+        ids,
+        tag,
+        before,
+        since,
+        yes,
+    } = options;
+
+    let selection = Selection::from_args(&ids, tag, before.as_deref(), since.as_deref());
+
+    let mut entries: Vec<DiaryEntry> = get_entries();
+    entries.sort();
+
+    let chosen: Vec<DiaryEntry> = match selection.is_empty() {
+        true => prompt_for_deletion(&entries)
+            .into_iter()
+            .map(|index| entries[index].clone())
+            .collect(),
+        false => {
+            let chosen = selection.apply(entries);
+            if chosen.is_empty() {
+                fail!("No entries match!");
+            }
+            if !yes && !confirm_selection(&chosen.iter().collect::<Vec<_>>(), "delete") {
+                std::process::exit(0);
+            }
+            chosen
+        }
+    };
+
+    report_backup(backup::automatic(config));
+    remove_ids(&chosen.iter().map(|entry| entry.id).collect::<Vec<_>>());
+    match chosen.len() {
+        1 => println!("Deleted 1 entry."),
+        count => println!("Deleted {count} entries."),
+    }
+}
+
+fn doctor(options: Doctor, config: &Config) {
+    let problems = diagnose();
+    if problems.is_empty() {
+        println!("No problems found.");
+        return;
+    }
+    for problem in &problems {
+        println!("- {problem}");
+    }
+    if !options.fix {
+        println!("Run `code_diary doctor --fix` to repair them.");
+        std::process::exit(1);
+    }
+
+    report_backup(backup::automatic(config));
+    for done in doctor::repair(&problems) {
+        println!("{done}.");
+    }
+}
+
+fn edit(options: Edit, config: &Config) {
+    let Edit { id, title, content } = options;
+
+    let mut entries = get_entries();
+    entries.sort();
+
+    let mut entry = match id {
+        Some(id) => match entries.into_iter().find(|entry| entry.id == id) {
+            Some(entry) => entry,
+            None => fail!("There is no entry with id {id}"),
+        },
+        None if entries.is_empty() => {
+            println!("You have no entries, silly!");
+            std::process::exit(1);
+        }
+        None => {
+            let index = prompt_for_editing(&entries);
+            entries.swap_remove(index as usize)
+        }
+    };
+
+    if title.is_none() && content.is_none() {
+        entry.content = prompt_for_content_in_editor(&config.editor_or_default(), &entry.content);
+    }
+    if let Some(title) = title {
+        entry.title = Some(title).filter(|title| !title.is_empty());
+    }
+    if let Some(content) = content {
+        entry.content = content;
+    }
+
+    update_entry(&entry);
+}
+
+fn hook(options: HookCommand, config: &Config) {
+    match options.action {
+        HookAction::Install(HookInstall { auto }) => {
+            let path = hook::install(auto).display().to_string();
+            println!("Installed the hook in {path}.");
+        }
+        HookAction::Uninstall(HookUninstall {}) => {
+            let path = hook::uninstall().display().to_string();
+            println!("Removed the hook from {path}.");
+        }
+        HookAction::Run(HookRun { auto }) => {
+            if git::rebasing() {
+                return;
+            }
+            let commit = match git::last_commit() {
+                Some(commit) => commit,
+                None => fail!("Could not read the latest commit"),
+            };
+            let template = hook::commit_template(&commit);
+            let entry = if auto {
+                let title = template.title.clone();
+                let content = Some(template.body.trim_end().to_string());
+                compose_entry(
+                    config,
+                    title,
+                    content,
+                    Vec::new(),
+                    Vec::new(),
+                    template,
+                    true,
+                )
+            } else if std::io::stdin().is_terminal() && prompt_to_record_commit(&commit.subject) {
+                compose_entry(config, None, None, Vec::new(), Vec::new(), template, false)
+            } else {
+                return;
+            };
+            store_entry(&entry);
+            // So that `import --git` does not record the commit a second time
+            record_commits(entry.id, &[commit.hash]);
+        }
+    }
+}
+
+fn import(options: Import) {
+    let Import {
+        git,
+        author,
+        since,
+        before,
+        group,
+    } = options;
+
+    let grouping = import::Grouping::from_name(&group);
+    let selection = Selection::from_args(&[], None, before.as_deref(), since.as_deref());
+    let entries = import::entries_from_git(
+        std::path::Path::new(&git),
+        author.as_deref(),
+        &selection,
+        grouping,
+    );
+
+    store_imported(&entries);
+    let commits: usize = entries.iter().map(|(_, hashes)| hashes.len()).sum();
+    match (commits, entries.len()) {
+        (0, _) => println!("There are no new commits to import."),
+        (1, _) => println!("Imported 1 commit."),
+        (commits, 1) => println!("Imported {commits} commits as 1 entry."),
+        (commits, count) => println!("Imported {commits} commits as {count} entries."),
+    }
+}
+
+fn init(options: Init, config: &Config) {
+    let Init { encrypt, cache_key } = options;
+
+    if cache_key && !encrypt {
+        fail!("--cache-key only makes sense with --encrypt");
+    }
+
+    if encrypt {
+        // No automatic backup here, as it would leave a plaintext copy behind
+        let sealed = crypto::enable(cache_key);
+        println!("Encrypted {sealed} entries.");
+        let backups = backup::all(config);
+        if backups.is_empty() {
+            return;
+        }
+        match std::io::stdin().is_terminal() && prompt_to_delete_plaintext_backups(backups.len()) {
+            true => {
+                for path in &backups {
+                    if let Err(e) = std::fs::remove_file(path) {
+                        let path = path.display();
+                        fail!("Could not remove the backup {path}: {e}");
+                    }
+                }
+                match backups.len() {
+                    1 => println!("Deleted 1 backup."),
+                    count => println!("Deleted {count} backups."),
+                }
+            }
+            false => {
+                let dir = config.backup_dir().display().to_string();
+                eprintln!("The backups in {dir} were taken before encryption and still hold your entries in plaintext; delete them once you no longer need them");
+            }
+        }
+    } else {
+        let entries = get_all_entries();
+        println!("Your diary is ready with {} entries.", entries.len());
+    }
+}
+
+fn list(options: List, config: &Config) {
+    let List {
+        limit,
+        all,
+        starred,
+    } = options;
+
+    let mut entries = get_entries();
+    if starred {
+        entries.retain(|entry| entry.starred);
+    }
+    entries.sort();
+    entries.reverse();
+    entries.sort_by_key(|entry| !entry.pinned);
+
+    if !all {
+        entries.truncate(limit.unwrap_or(config.list_limit()) as usize);
+    }
+
+    let lines: Vec<String> = entries.iter().map(|entry| entry.show_summary()).collect();
+    pager::page(&lines.join("\n"));
 }
 
-fn delete(options: Delete) {
-    let Delete { index } = options;
+fn notebook(options: Notebook) {
+    match options.action {
+        NotebookAction::New(NotebookNew { name }) => {
+            create_notebook(&name);
+            println!("Created notebook '{name}'.");
+        }
+        NotebookAction::List(NotebookList {}) => {
+            let current = current_notebook();
+            let lines: Vec<String> = get_notebooks()
+                .into_iter()
+                .map(|(name, count)| {
+                    let marker = if name == current { "*" } else { " " };
+                    let count = theme::paint(theme::Role::Muted, &format!("({count})"));
+                    format!(
+                        "{marker} {} {count}",
+                        theme::paint(theme::Role::Title, &name)
+                    )
+                })
+                .collect();
+            pager::page(&lines.join("\n"));
+        }
+        NotebookAction::Move(NotebookMove { id, notebook }) => {
+            move_entry(id, &notebook);
+            println!("Moved entry {id} to '{notebook}'.");
+        }
+    }
+}
+
+fn pin(options: Pin) {
+    let Pin { id, undo } = options;
+    set_pinned(id, !undo);
+    match undo {
+        true => println!("Unpinned entry {id}."),
+        false => println!("Pinned entry {id}."),
+    }
+}
+
+fn star(options: Star) {
+    let Star { id, undo } = options;
+    set_starred(id, !undo);
+    match undo {
+        true => println!("Unstarred entry {id}."),
+        false => println!("Starred entry {id}."),
+    }
+}
+
+fn tag(options: Tag) {
+    let Tag {
+        ids,
+        add,
+        remove,
+        tag,
+        before,
+        since,
+    } = options;
+
+    if add.is_empty() && remove.is_empty() {
+        fail!("Nothing to do! Pass --add or --remove");
+    }
+
+    let selection = Selection::from_args(&ids, tag, before.as_deref(), since.as_deref());
 
     let mut entries: Vec<DiaryEntry> = get_entries();
     entries.sort();
-    let index = match index {
-        Some(index) => index,
-        None => prompt_for_deletion(&entries),
+
+    let chosen: Vec<DiaryEntry> = match selection.is_empty() {
+        true => prompt_for_selection(&entries, "Which items do you want to retag?")
+            .into_iter()
+            .map(|index| entries[index].clone())
+            .collect(),
+        false => selection.apply(entries),
     };
 
-    if index >= entries.len() as u64 {
-        fail!("Index too big!");
+    if chosen.is_empty() {
+        fail!("No entries match!");
     }
 
-    remove_id(entries[index as usize].id);
+    for mut entry in chosen.iter().cloned() {
+        entry.tags.retain(|tag| !remove.contains(tag));
+        for tag in &add {
+            if !entry.tags.contains(tag) {
+                entry.tags.push(tag.clone());
+            }
+        }
+        update_entry(&entry);
+    }
+    match chosen.len() {
+        1 => println!("Retagged 1 entry."),
+        count => println!("Retagged {count} entries."),
+    }
 }
 
 fn view(options: View) {
-    let View { index } = options;
+    let View { id, raw } = options;
 
-    let entries = get_entries();
+    let mut entries = get_entries();
+    entries.sort();
 
-    if entries.len() == 0 {
-        println!("You have no entries, silly!");
-        std::process::exit(1);
+    let entry = match id {
+        Some(id) => match entries.into_iter().find(|entry| entry.id == id) {
+            Some(entry) => entry,
+            None => fail!("There is no entry with id {id}"),
+        },
+        None if entries.is_empty() => {
+            println!("You have no entries, silly!");
+            std::process::exit(1);
+        }
+        None => {
+            let index = prompt_for_viewing(&entries);
+            entries.swap_remove(index as usize)
+        }
+    };
+
+    let links = links::show(&entry, &get_links(), &get_all_entries());
+    match raw {
+        true => pager::page(&format!("{}{links}", entry.show_raw())),
+        false => pager::page(&format!(
+            "{}{links}",
+            entry.show_rendered(markdown::terminal_width())
+        )),
+    }
+}
+
+fn links(options: Links) {
+    let Links { id, depth } = options;
+
+    let entries = get_all_entries();
+    let entry = match entries.iter().find(|entry| entry.id == id) {
+        Some(entry) => entry,
+        None => fail!("There is no entry with id {id}"),
+    };
+    pager::page(&links::graph(entry, &get_links(), &entries, depth));
+}
+
+fn calendar(options: Calendar) {
+    let Calendar { month, day } = options;
+
+    let today = chrono::Local::now().date_naive();
+    let month = match month {
+        Some(month) => match calendar::parse_month(&month) {
+            Some(month) => month,
+            None => fail!("'{month}' is not a month like 2023-01"),
+        },
+        None => today - chrono::Duration::days(today.day0() as i64),
+    };
+
+    let mut entries = get_entries();
+    entries.sort();
+
+    let day = match day {
+        Some(day) => match calendar::parse_day(&day, month) {
+            Some(day) => day,
+            None => fail!("'{day}' is not a day of the month or a date like 2023-01-31"),
+        },
+        None => {
+            let counts = calendar::entries_per_day(&entries, month);
+            println!("{}", calendar::show_month(month, &counts, today));
+            let interactive = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+            if counts.is_empty() || !interactive {
+                return;
+            }
+            let days: Vec<_> = counts.into_iter().collect();
+            match prompt_for_day(&days) {
+                Some(day) => day,
+                None => return,
+            }
+        }
+    };
+
+    let lines: Vec<String> = entries
+        .iter()
+        .filter(|entry| calendar::written_on(entry, day))
+        .map(|entry| entry.show_summary())
+        .collect();
+    if lines.is_empty() {
+        println!("You wrote nothing on {day}.");
+        return;
+    }
+    let heading = theme::paint(theme::Role::Heading, &day.format("%A %Y-%m-%d").to_string());
+    pager::page(&format!("{heading}\n{}", lines.join("\n")));
+}
+
+fn report(options: Report) {
+    let Report {
+        since,
+        before,
+        week,
+        tag,
+        by,
+        format,
+    } = options;
+
+    let today = chrono::Local::now().date_naive();
+    let since = match (week, since) {
+        (true, Some(_)) => fail!("--week and --since cannot be used together"),
+        (true, None) => {
+            let monday =
+                today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+            monday.to_string()
+        }
+        (false, since) => since.unwrap_or_else(|| String::from("today")),
+    };
+    let selection = Selection::from_args(&[], tag, before.as_deref(), Some(&since));
+    let grouping = report::Grouping::from_name(by.as_deref().unwrap_or("day"));
+    let template = report::Template::load(format.as_deref().unwrap_or("markdown"));
+
+    let since = match selection.since.and_then(|since| since.to_datetime()) {
+        Some(since) => since.date(),
+        None => today,
+    };
+    let until = match selection.before.and_then(|before| before.to_datetime()) {
+        Some(before) => before.date().pred_opt().unwrap_or(since),
+        None => today,
+    };
+
+    let entries = selection.apply(get_entries());
+    if entries.is_empty() {
+        println!("Nothing was written from {since} to {until}.");
+        return;
+    }
+    println!("{}", template.render(&entries, grouping, since, until));
+}
+
+fn template(options: TemplateCommand, config: &Config) {
+    match options.action {
+        TemplateAction::List(TemplateList {}) => {
+            let lines: Vec<String> = templates::names()
+                .into_iter()
+                .map(|(name, starter)| match starter {
+                    true => format!("{name} {}", theme::paint(theme::Role::Muted, "(built in)")),
+                    false => name,
+                })
+                .collect();
+            pager::page(&lines.join("\n"));
+        }
+        TemplateAction::New(TemplateNew { name }) => {
+            let path = templates::path(&name);
+            if path.exists() {
+                fail!("The template '{name}' already exists; use `template edit {name}`");
+            }
+            let text = templates::starter(&name).unwrap_or("+++\ntitle = \"\"\ntags = []\n+++\n");
+            write_template(&path, text);
+            edit_template(config, &name, &path);
+        }
+        TemplateAction::Edit(TemplateEdit { name }) => {
+            let path = templates::path(&name);
+            if !path.exists() {
+                // Built-in templates get a file the first time they are edited
+                match templates::starter(&name) {
+                    Some(text) => write_template(&path, text),
+                    None => fail!("There is no template '{name}'; use `template new {name}`"),
+                }
+            }
+            edit_template(config, &name, &path);
+        }
+    }
+}
+
+fn write_template(path: &std::path::Path, text: &str) {
+    if let Some(dir) = path.parent() {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            let dir = dir.display();
+            fail!("Could not create {dir}: {e}");
+        }
+    }
+    if let Err(e) = std::fs::write(path, text) {
+        let path = path.display();
+        fail!("Could not write {path}: {e}");
+    }
+}
+
+fn edit_template(config: &Config, name: &str, path: &std::path::Path) {
+    if !edit_file(&config.editor_or_default(), path) {
+        fail!("The editor exited with an error");
+    }
+    let path = path.display();
+    println!("Saved template '{name}' to {path}.");
+}
+
+fn today(options: Today, config: &Config) {
+    let Today { text } = options;
+    let text = text.join(" ");
+
+    let date = chrono::Local::now().date_naive();
+    let existing = get_entries_on(date)
+        .into_iter()
+        .filter(|entry| entry.tags.iter().any(|tag| tag == TODAY_TAG))
+        .min();
+    let is_new = existing.is_none();
+    let mut entry = existing.unwrap_or_else(|| {
+        let mut entry = DiaryEntry::new(&date.format("%A %Y-%m-%d").to_string(), "");
+        entry.tags.push(String::from(TODAY_TAG));
+        entry
+    });
+
+    match text.trim() {
+        "" => {
+            entry.content =
+                prompt_for_content_in_editor(&config.editor_or_default(), &entry.content)
+        }
+        text => entry.append_line(text),
     }
 
-    let index: u64 = match index {
-        Some(index) => index,
-        None => prompt_for_viewing(&entries),
+    match (is_new, entry.content.is_empty()) {
+        (true, true) => println!("Nothing was written, so no entry was added."),
+        (true, false) => store_entry(&entry),
+        (false, _) => update_entry(&entry),
+    }
+}
+
+fn show_ref(options: ShowRef) {
+    let ShowRef { id } = options;
+
+    let entry = match get_entries().into_iter().find(|entry| entry.id == id) {
+        Some(entry) => entry,
+        None => fail!("There is no entry with id {id}"),
     };
+    if entry.refs.is_empty() {
+        fail!("Entry {id} does not refer to any code");
+    }
+
+    let blocks: Vec<String> = entry
+        .refs
+        .iter()
+        .map(|file_ref| {
+            let header = theme::paint(theme::Role::Heading, &file_ref.to_string());
+            match file_ref.read() {
+                Ok(lines) => format!("{header}\n{lines}"),
+                Err(e) => format!("{header}\n{}", theme::paint(theme::Role::Muted, &e)),
+            }
+        })
+        .collect();
+    pager::page(&blocks.join("\n\n"));
+}
+
+fn start(options: Start) {
+    let Start { title, tag } = options;
 
-    if index >= entries.len() as u64 {
-        fail!("Index too big!");
+    if let Some(session) = sessions::Session::running() {
+        let title = session.title.as_deref().unwrap_or("Untitled");
+        let since = session.start().display();
+        fail!("Already working on {title} since {since}; stop it first");
     }
+    let title = Some(title.join(" ")).filter(|title| !title.trim().is_empty());
+    let session = sessions::Session {
+        title,
+        tags: tag,
+        notebook: current_notebook(),
+        repo: git::repo_name(),
+        start: timestamps::TimeStamp::new().to_string(),
+    };
+    session.save();
+    let title = session.title.as_deref().unwrap_or("Untitled");
+    println!("Started {title}.");
+}
+
+fn stop(options: Stop, config: &Config) {
+    let Stop { content } = options;
+    let added_from_args = content.is_some();
 
-    println!("{}", entries[index as usize].show_detail());
+    let session = match sessions::Session::running() {
+        Some(session) => session,
+        None => fail!("No session is running; start one with `start`"),
+    };
+    let ended = timestamps::TimeStamp::new();
+    let template = templates::EntryTemplate {
+        title: None,
+        tags: session.tags.clone(),
+        body: String::new(),
+    };
+    let mut entry = compose_entry(
+        config,
+        session.title.clone(),
+        content,
+        Vec::new(),
+        Vec::new(),
+        template,
+        added_from_args,
+    );
+    entry.date = session.start();
+    entry.ended = Some(ended);
+    entry.notebook = session.notebook;
+    entry.repo = session.repo;
+    store_entry(&entry);
+    sessions::Session::clear();
+
+    let duration = sessions::show_duration(entry.duration().unwrap_or_else(chrono::Duration::zero));
+    println!("Recorded {duration}.");
+}
+
+fn stats(options: Stats) {
+    let entries = get_entries();
+    if entries.is_empty() {
+        println!("You have no entries, silly!");
+        std::process::exit(1);
+    }
+
+    if let Some(field) = options.field {
+        match stats::show_field(&entries, &field, markdown::terminal_width()) {
+            Some(chart) => pager::page(&chart),
+            None => fail!("No entries rate {field}"),
+        }
+        return;
+    }
+
+    let today = chrono::Local::now().date_naive();
+    let stats = stats::Stats::compute(&entries, today);
+    pager::page(&stats.show(today, markdown::terminal_width()));
+}
+
+fn completions(options: Completions, config: &Config) {
+    let Completions { shell, candidates } = options;
+    match (shell, candidates) {
+        (_, Some(kind)) => completions::print_candidates(&kind, config),
+        (Some(shell), None) => print!("{}", completions::script(&shell)),
+        (None, None) => fail!("Which shell? Expected one of bash, zsh or fish"),
+    }
 }
 
 fn main() {
     let args: Cli = argh::from_env();
+    let config = Config::load();
+
+    if args.no_pager {
+        pager::disable();
+    }
+
+    // The configuration must stay editable even if it points at a missing
+    // notebook or database, so handle it before touching either.
+    if let Some(Action::Config(options)) = args.action {
+        return manage_config(options, config);
+    }
+
+    theme::init(&config.theme(), args.color.as_deref());
+
+    set_db_path(config.db_path());
+    if let Some(format) = &config.date_format {
+        timestamps::set_display_format(format);
+    }
+
+    let selected_notebook = args.notebook.clone().or(config.default_notebook.clone());
+    if let Some(notebook) = &selected_notebook {
+        select_notebook(notebook);
+    }
 
     let action = match args.action {
         Some(action) => action,
-        None => prompt_action(),
+        None => {
+            let notebooks = get_notebooks();
+            if selected_notebook.is_none() && notebooks.len() > 1 {
+                select_notebook(&prompt_for_notebook(&notebooks));
+            }
+            prompt_action()
+        }
     };
 
     match action {
-        Action::Add(options) => add(options),
-        Action::Delete(options) => delete(options),
+        Action::Add(options) => add(options, &config),
+        Action::Attachment(options) => attachment(options),
+        Action::Backup(options) => backup(options, &config),
+        Action::Calendar(options) => calendar(options),
+        Action::Completions(options) => completions(options, &config),
+        Action::Config(_) => unreachable!(),
+        Action::Delete(options) => delete(options, &config),
+        Action::Doctor(options) => doctor(options, &config),
+        Action::Edit(options) => edit(options, &config),
+        Action::Hook(options) => hook(options, &config),
+        Action::Import(options) => import(options),
+        Action::Init(options) => init(options, &config),
+        Action::Links(options) => links(options),
+        Action::List(options) => list(options, &config),
+        Action::Notebook(options) => notebook(options),
+        Action::Pin(options) => pin(options),
+        Action::Report(options) => report(options),
+        Action::Restore(options) => restore(options, &config),
+        Action::ShowRef(options) => show_ref(options),
+        Action::Start(options) => start(options),
+        Action::Star(options) => star(options),
+        Action::Stats(options) => stats(options),
+        Action::Stop(options) => stop(options, &config),
+        Action::Tag(options) => tag(options),
+        Action::Template(options) => template(options, &config),
+        Action::Today(options) => today(options, &config),
+        Action::Tui(Tui {}) => tui::run(&config),
         Action::View(options) => view(options),
     }
 }
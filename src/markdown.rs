@@ -0,0 +1,423 @@
+//! Renders the Markdown in entry contents for the terminal: bold headings,
+//! indented lists and quotes, paragraphs wrapped to the terminal width, and
+//! code blocks highlighted with `syntect`'s bundled syntaxes.
+
+use std::sync::OnceLock;
+
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Parser, Tag, TagEnd};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::SyntaxSet;
+use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
+use unicode_width::UnicodeWidthStr;
+
+use crate::theme::{self, Role};
+
+/// Used when the terminal width cannot be determined
+const DEFAULT_WIDTH: usize = 80;
+
+/// The languages code blocks are highlighted in, loaded on first use
+static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+/// The themes code blocks are highlighted with, loaded on first use
+static CODE_THEMES: OnceLock<ThemeSet> = OnceLock::new();
+
+const RESET: &str = "\x1b[0m";
+
+/// Returns the width of the terminal, or a sensible default if stdout is not a
+/// terminal
+pub fn terminal_width() -> usize {
+    match ratatui::crossterm::terminal::size() {
+        Ok((columns, _)) if columns > 0 => columns as usize,
+        _ => DEFAULT_WIDTH,
+    }
+}
+
+/// Renders `text` as Markdown, wrapping paragraphs to `width` columns
+pub fn render(text: &str, width: usize) -> String {
+    let mut renderer = Renderer::new(width);
+    for event in Parser::new(text) {
+        renderer.event(event);
+    }
+    renderer.flush();
+    renderer.out.trim_end().to_string()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    code: bool,
+    link: bool,
+    heading: bool,
+    underline: bool,
+}
+
+impl Style {
+    fn paint(&self, text: &str) -> String {
+        let mut codes = Vec::new();
+        if self.bold {
+            codes.push("1");
+        }
+        if self.italic {
+            codes.push("3");
+        }
+        if self.underline {
+            codes.push("4");
+        }
+        if self.link {
+            codes.push(theme::codes(Role::Link));
+        }
+        if self.heading {
+            codes.push(theme::codes(Role::Heading));
+        }
+        if self.code {
+            codes.push(theme::codes(Role::Code));
+        }
+        theme::sgr(&codes, text)
+    }
+}
+
+/// A word as it will be printed, and how many columns it takes up
+struct Word {
+    painted: String,
+    width: usize,
+}
+
+struct Renderer {
+    width: usize,
+    out: String,
+    style: Style,
+    /// Words of the paragraph being built
+    words: Vec<Word>,
+    /// Whether the last text pushed ended a word
+    word_done: bool,
+    /// Printed before every line of the current block
+    indent: Vec<String>,
+    /// Printed instead of the innermost indent on the next line, e.g. a bullet
+    marker: Option<String>,
+    /// For each open list, the number of the next item if it is ordered
+    lists: Vec<Option<u64>>,
+    /// The language and text of the code block being read
+    code: Option<(String, String)>,
+    link: Option<String>,
+}
+
+impl Renderer {
+    fn new(width: usize) -> Self {
+        Self {
+            width,
+            out: String::new(),
+            style: Style::default(),
+            words: Vec::new(),
+            word_done: true,
+            indent: Vec::new(),
+            marker: None,
+            lists: Vec::new(),
+            code: None,
+            link: None,
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        if let Some((_, code)) = &mut self.code {
+            match event {
+                Event::Text(text) => code.push_str(&text),
+                Event::End(TagEnd::CodeBlock) => self.end_code_block(),
+                _ => (),
+            }
+            return;
+        }
+
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => self.push_text(&text),
+            Event::Code(text) => {
+                self.style.code = true;
+                self.push_text(&text);
+                self.style.code = false;
+            }
+            Event::SoftBreak => self.word_done = true,
+            Event::HardBreak => self.flush(),
+            Event::Rule => {
+                self.flush();
+                let rule = "─".repeat(self.width.saturating_sub(self.indent_width()).max(3));
+                self.line(&theme::paint(Role::Muted, &rule));
+                self.blank();
+            }
+            Event::TaskListMarker(done) => self.push_text(if done { "[x] " } else { "[ ] " }),
+            Event::Html(html) | Event::InlineHtml(html) => self.push_text(&html),
+            _ => (),
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.flush();
+                self.style.heading = true;
+                if level == HeadingLevel::H1 {
+                    self.style.underline = true;
+                }
+            }
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.indent
+                    .push(format!("{} ", theme::paint(Role::Muted, "│")));
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush();
+                let language = match kind {
+                    CodeBlockKind::Fenced(language) => language.to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code = Some((language, String::new()));
+            }
+            Tag::List(start) => {
+                self.flush();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush();
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => String::from("• "),
+                };
+                self.indent.push(" ".repeat(marker.width()));
+                self.marker = Some(marker);
+            }
+            Tag::Emphasis => self.style.italic = true,
+            Tag::Strong => self.style.bold = true,
+            Tag::Link { dest_url, .. } => {
+                self.style.link = true;
+                self.link = Some(dest_url.to_string());
+            }
+            _ => (),
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Heading(_) => {
+                self.flush();
+                self.style = Style::default();
+                self.blank();
+            }
+            TagEnd::Paragraph => {
+                self.flush();
+                if self.lists.is_empty() {
+                    self.blank();
+                }
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush();
+                self.indent.pop();
+                self.blank();
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank();
+                }
+            }
+            TagEnd::Item => {
+                self.flush();
+                self.indent.pop();
+                self.marker = None;
+            }
+            TagEnd::Emphasis => self.style.italic = false,
+            TagEnd::Strong => self.style.bold = false,
+            TagEnd::Link => {
+                self.style.link = false;
+                if let Some(url) = self.link.take() {
+                    self.word_done = true;
+                    self.push_word(
+                        &theme::paint(Role::Muted, &format!("({url})")),
+                        url.width() + 2,
+                    );
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        for (index, piece) in text.split(char::is_whitespace).enumerate() {
+            if index > 0 {
+                self.word_done = true;
+            }
+            if !piece.is_empty() {
+                self.push_word(&self.style.paint(piece), piece.width());
+            }
+        }
+        if text.ends_with(char::is_whitespace) {
+            self.word_done = true;
+        }
+    }
+
+    fn push_word(&mut self, painted: &str, width: usize) {
+        match self.words.last_mut() {
+            Some(word) if !self.word_done => {
+                word.painted.push_str(painted);
+                word.width += width;
+            }
+            _ => self.words.push(Word {
+                painted: painted.to_string(),
+                width,
+            }),
+        }
+        self.word_done = false;
+    }
+
+    /// Wraps and prints the words collected so far
+    fn flush(&mut self) {
+        if self.words.is_empty() {
+            return;
+        }
+        let available = self.width.saturating_sub(self.indent_width()).max(20);
+        let mut line = String::new();
+        let mut line_width = 0;
+        for word in std::mem::take(&mut self.words) {
+            if line_width > 0 && line_width + 1 + word.width > available {
+                self.line(&line);
+                line.clear();
+                line_width = 0;
+            }
+            if line_width > 0 {
+                line.push(' ');
+                line_width += 1;
+            }
+            line.push_str(&word.painted);
+            line_width += word.width;
+        }
+        self.line(&line);
+        self.word_done = true;
+    }
+
+    fn end_code_block(&mut self) {
+        let (language, code) = match self.code.take() {
+            Some(code) => code,
+            None => return,
+        };
+        for line in highlight(&code, &language).lines() {
+            match theme::enabled() {
+                true => self.line(&format!("  {line}{RESET}")),
+                false => self.line(&format!("  {line}")),
+            }
+        }
+        self.blank();
+    }
+
+    fn indent_width(&self) -> usize {
+        self.indent.iter().map(|indent| visible_width(indent)).sum()
+    }
+
+    /// Prints one line behind the current indentation
+    fn line(&mut self, text: &str) {
+        let depth = self.indent.len();
+        for (index, indent) in self.indent.iter().enumerate() {
+            match &self.marker {
+                Some(marker) if index + 1 == depth => self.out.push_str(marker),
+                _ => self.out.push_str(indent),
+            }
+        }
+        self.marker = None;
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn blank(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+}
+
+/// Highlights `code` written in `language` (a name or file extension), or
+/// returns it unchanged if the language is unknown or the theme does not
+/// highlight code
+fn highlight(code: &str, language: &str) -> String {
+    let code_theme = match theme::current().syntax {
+        Some(code_theme) if theme::enabled() => code_theme,
+        _ => return code.to_string(),
+    };
+    let syntaxes = SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines);
+    let syntax = match syntaxes.find_syntax_by_token(language) {
+        Some(syntax) if !language.is_empty() => syntax,
+        _ => return code.to_string(),
+    };
+    let themes = CODE_THEMES.get_or_init(ThemeSet::load_defaults);
+    let mut highlighter = HighlightLines::new(syntax, &themes.themes[code_theme]);
+
+    let mut out = String::new();
+    for line in LinesWithEndings::from(code) {
+        match highlighter.highlight_line(line, syntaxes) {
+            Ok(ranges) => out.push_str(&as_24_bit_terminal_escaped(&ranges, false)),
+            Err(_) => out.push_str(line),
+        }
+    }
+    out
+}
+
+/// The width of `text` without its ANSI escape sequences
+pub fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut in_escape = false;
+    for c in text.chars() {
+        match c {
+            '\x1b' => in_escape = true,
+            'm' if in_escape => in_escape = false,
+            _ if in_escape => (),
+            c => width += c.to_string().width(),
+        }
+    }
+    width
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(text: &str, width: usize) -> String {
+        theme::init("dark", Some("never"));
+        render(text, width)
+    }
+
+    #[test]
+    fn paragraphs_wrap_to_the_width() {
+        let text = "one two three four five six seven eight nine ten";
+        assert_eq!(
+            plain(text, 20),
+            "one two three four\nfive six seven eight\nnine ten"
+        );
+        assert_eq!(plain("first\n\nsecond", 80), "first\n\nsecond");
+    }
+
+    #[test]
+    fn lists_get_bullets_and_numbers() {
+        assert_eq!(plain("- a\n- b", 80), "• a\n• b");
+        assert_eq!(plain("3. x\n4. y", 80), "3. x\n4. y");
+        assert_eq!(plain("- a\n  - b", 80), "• a\n  • b");
+    }
+
+    #[test]
+    fn quotes_links_and_code_blocks() {
+        assert_eq!(plain("> quoted", 80), "│ quoted");
+        assert_eq!(
+            plain("[site](https://example.com)", 80),
+            "site (https://example.com)"
+        );
+        assert_eq!(plain("```\nlet x = 1;\n```", 80), "  let x = 1;");
+        assert_eq!(plain("# Title\n\nbody", 80), "Title\n\nbody");
+    }
+
+    #[test]
+    fn visible_width_skips_escapes() {
+        assert_eq!(visible_width("\x1b[1;34mtitle\x1b[0m"), 5);
+        assert_eq!(visible_width("日本"), 4);
+    }
+}
@@ -0,0 +1,68 @@
+//! Sends output that does not fit on the screen through `$PAGER`.
+
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::markdown::visible_width;
+
+/// Used when `$PAGER` is not set. `-R` keeps the colors.
+const DEFAULT_PAGER: &str = "less -R";
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turns paging off for the rest of the program, e.g. for `--no-pager`
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// Prints `text`, through the pager if paging is enabled, stdout is a
+/// terminal, and `text` is taller than the terminal.
+pub fn page(text: &str) {
+    if !should_page(text) {
+        println!("{text}");
+        return;
+    }
+
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| String::from(DEFAULT_PAGER));
+    let mut words = pager.split_whitespace();
+    let program = match words.next() {
+        Some(program) => program,
+        None => {
+            println!("{text}");
+            return;
+        }
+    };
+
+    let child = std::process::Command::new(program)
+        .args(words)
+        .stdin(std::process::Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(_) => {
+            println!("{text}");
+            return;
+        }
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The user may quit the pager before reading everything
+        let _ = writeln!(stdin, "{text}");
+    }
+    let _ = child.wait();
+}
+
+fn should_page(text: &str) -> bool {
+    if !ENABLED.load(Ordering::Relaxed) || !std::io::stdout().is_terminal() {
+        return false;
+    }
+    let (columns, rows) = match ratatui::crossterm::terminal::size() {
+        Ok((columns, rows)) if columns > 0 && rows > 0 => (columns as usize, rows as usize),
+        _ => return false,
+    };
+    let height: usize = text
+        .lines()
+        .map(|line| visible_width(line).div_ceil(columns).max(1))
+        .sum();
+    height >= rows
+}
@@ -0,0 +1,139 @@
+//! References from entries to places in the code, like
+//! `src/parser.rs:120-140`, given with `code_diary add --ref`.
+//!
+//! A reference inside a git repository remembers the repository and the
+//! commit checked out when the entry was written, so `code_diary show-ref`
+//! can print the lines as they were then, even after the file has changed.
+
+use std::path::{Path, PathBuf};
+
+use crate::theme::{paint, Role};
+use crate::{fail, git};
+
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
+/// A file, or a range of lines in one, that an entry is about
+pub struct FileRef {
+    /// The path of the file, relative to `root` if there is one and absolute
+    /// otherwise
+    pub path: String,
+    /// The first and last line, counting from 1, or `None` for the whole file
+    pub lines: Option<(u32, u32)>,
+    /// The top level directory of the repository the file is in
+    pub root: Option<String>,
+    /// The full hash of the commit checked out when the reference was made
+    pub commit: Option<String>,
+}
+
+impl FileRef {
+    /// Parses `path`, `path:line` or `path:first-last` and locates the file
+    /// in the repository around it
+    /// # Panics
+    /// Will 'gracefully' exit if the file does not exist or the lines are
+    /// malformed
+    pub fn resolve(arg: &str) -> Self {
+        let (path, lines) = match arg.rsplit_once(':') {
+            Some((path, range)) if range.chars().all(|c| c.is_ascii_digit() || c == '-') => {
+                (path, Some(parse_lines(range, arg)))
+            }
+            _ => (arg, None),
+        };
+        let absolute = match Path::new(path).canonicalize() {
+            Ok(absolute) => absolute,
+            Err(e) => fail!("Cannot refer to {path}: {e}"),
+        };
+
+        let dir = absolute.parent().unwrap_or(Path::new("/"));
+        let root = git::git(Some(dir), &["rev-parse", "--show-toplevel"]).map(PathBuf::from);
+        let relative = root.as_ref().and_then(|root| {
+            let root = root.canonicalize().ok()?;
+            Some(absolute.strip_prefix(root).ok()?.to_path_buf())
+        });
+        match (root, relative) {
+            (Some(root), Some(relative)) => Self {
+                path: relative.to_string_lossy().replace('\\', "/"),
+                lines,
+                commit: git::git(Some(&root), &["rev-parse", "HEAD"]),
+                root: Some(root.display().to_string()),
+            },
+            _ => Self {
+                path: absolute.display().to_string(),
+                lines,
+                root: None,
+                commit: None,
+            },
+        }
+    }
+
+    /// Returns the referenced lines, numbered, as they were at the commit, or
+    /// as they are now for a file outside a repository
+    pub fn read(&self) -> Result<String, String> {
+        let text = match (&self.root, &self.commit) {
+            (Some(root), Some(commit)) => {
+                let object = format!("{commit}:{}", self.path);
+                match git::git(Some(Path::new(root)), &["show", &object]) {
+                    Some(text) => text,
+                    None => return Err(format!("Could not read {object} in {root}")),
+                }
+            }
+            _ => match std::fs::read_to_string(self.location()) {
+                Ok(text) => text,
+                Err(e) => return Err(format!("Could not read {}: {e}", self.path)),
+            },
+        };
+
+        let (first, last) = self.lines.unwrap_or((1, u32::MAX));
+        let width = last.min(text.lines().count() as u32).to_string().len();
+        let lines: Vec<String> = text
+            .lines()
+            .zip(1..)
+            .filter(|(_, number)| (first..=last).contains(number))
+            .map(|(line, number)| {
+                let number = paint(Role::Muted, &format!("{number:>width$}"));
+                format!("{number} {line}").trim_end().to_string()
+            })
+            .collect();
+        match lines.is_empty() {
+            true => Err(format!("{} has no line {first}", self.path)),
+            false => Ok(lines.join("\n")),
+        }
+    }
+
+    /// Where the file is in the working tree
+    fn location(&self) -> PathBuf {
+        match &self.root {
+            Some(root) => Path::new(root).join(&self.path),
+            None => PathBuf::from(&self.path),
+        }
+    }
+}
+
+/// Shows the reference as `path:first-last`, followed by the abbreviated
+/// commit
+impl std::fmt::Display for FileRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path)?;
+        match self.lines {
+            Some((first, last)) if first == last => write!(f, ":{first}")?,
+            Some((first, last)) => write!(f, ":{first}-{last}")?,
+            None => (),
+        }
+        match &self.commit {
+            Some(commit) => write!(f, " @ {}", &commit[..commit.len().min(7)]),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parses `120` or `120-140` from the reference `arg`
+/// # Panics
+/// Will 'gracefully' exit if the range is malformed or backwards
+fn parse_lines(range: &str, arg: &str) -> (u32, u32) {
+    let parsed = match range.split_once('-') {
+        Some((first, last)) => first.parse().ok().zip(last.parse().ok()),
+        None => range.parse().ok().map(|line| (line, line)),
+    };
+    match parsed {
+        Some((first, last)) if 0 < first && first <= last => (first, last),
+        _ => fail!("'{arg}' does not give lines like file.rs:12 or file.rs:120-140"),
+    }
+}
@@ -0,0 +1,410 @@
+//! Summaries of recent entries for pasting into stand-ups and status updates,
+//! printed by `code_diary report`.
+//!
+//! A report is laid out by a [`Template`]: `markdown` and `text` are built in,
+//! and users can add their own as TOML files in the `reports` directory next
+//! to the configuration file, e.g. `~/.config/code_diary/reports/standup.toml`:
+//!
+//! ```toml
+//! header = "Stand-up {{until}}"
+//! group = "{{group}}:"
+//! entry = "  - {{summary}}"
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::attachments::show_size;
+use crate::config::config_dir;
+use crate::diaryentry::DiaryEntry;
+use crate::fail;
+use crate::sessions::{self, show_duration};
+use crate::templates::{fill, stems};
+
+/// The templates that need no file
+pub const BUILT_IN: &[&str] = &["markdown", "text"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How entries are grouped in a report
+pub enum Grouping {
+    /// By the day they were written, oldest first
+    Day,
+    /// By the git repository they were written in
+    Repo,
+    /// By tag; entries with several tags appear under each
+    Tag,
+}
+
+impl Grouping {
+    /// Parses `day`, `repo` or `tag`
+    /// # Panics
+    /// Will 'gracefully' exit if `name` is none of those
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "day" => Self::Day,
+            "repo" => Self::Repo,
+            "tag" => Self::Tag,
+            _ => fail!("Cannot group by '{name}', expected day, repo or tag"),
+        }
+    }
+
+    /// The names of the groups `entry` belongs in. Each name sorts in the
+    /// order its group should appear.
+    fn keys(&self, entry: &DiaryEntry) -> Vec<(String, String)> {
+        match self {
+            Self::Day => match entry.date.to_datetime() {
+                Some(datetime) => vec![(
+                    datetime.format("%Y-%m-%d").to_string(),
+                    datetime.format("%A %Y-%m-%d").to_string(),
+                )],
+                None => vec![(String::from("~"), String::from("Unknown date"))],
+            },
+            Self::Repo => match &entry.repo {
+                Some(repo) => vec![(repo.to_lowercase(), repo.clone())],
+                None => vec![(String::from("~"), String::from("No repository"))],
+            },
+            Self::Tag if entry.tags.is_empty() => {
+                vec![(String::from("~"), String::from("Untagged"))]
+            }
+            Self::Tag => entry
+                .tags
+                .iter()
+                .map(|tag| (tag.to_lowercase(), format!("#{tag}")))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+/// The layout of a report. Every line is filled in with `{{placeholders}}`:
+///
+/// - `header`: `{{since}}`, `{{until}}` and `{{count}}`, the number of entries
+/// - `group`: `{{group}}` and `{{count}}`
+/// - `entry`: `{{id}}`, `{{title}}`, `{{content}}`, `{{summary}}` (the first
+///   line of the content), `{{date}}`, `{{time}}`, `{{tags}}`, `{{repo}}`,
+///   `{{notebook}}`, `{{duration}}`, the time tracked with `start` and
+///   `stop`, and `{{attachments}}`, the names and sizes of attached files
+/// - `total`: `{{name}}`, a tag or repository, and `{{duration}}`, the time
+///   tracked on it. Totals follow the groups, under the `group` line.
+///
+/// Empty lines are left out.
+pub struct Template {
+    /// Printed once at the top
+    pub header: String,
+    /// Printed before the entries of each group
+    pub group: String,
+    /// Printed for each entry
+    pub entry: String,
+    /// Printed for each tag and repository time was tracked on
+    pub total: String,
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Self::markdown()
+    }
+}
+
+impl Template {
+    fn markdown() -> Self {
+        Self {
+            header: String::from("# Report for {{since}} to {{until}}"),
+            group: String::from("## {{group}}"),
+            entry: String::from("- **{{title}}**{{tags}}: {{content}}{{attachments}}"),
+            total: String::from("- {{name}}: {{duration}}"),
+        }
+    }
+
+    fn text() -> Self {
+        Self {
+            header: String::from("Report for {{since}} to {{until}}"),
+            group: String::from("{{group}}"),
+            entry: String::from("  * {{title}}{{tags}}: {{content}}{{attachments}}"),
+            total: String::from("  {{name}}: {{duration}}"),
+        }
+    }
+
+    /// Returns the built-in template called `name`, or loads it from the
+    /// `reports` directory
+    /// # Panics
+    /// Will 'gracefully' exit if there is no such template or it is malformed
+    pub fn load(name: &str) -> Self {
+        match name {
+            "markdown" => return Self::markdown(),
+            "text" => return Self::text(),
+            _ => (),
+        }
+        let path = template_path(name);
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => {
+                let names = names().join(", ");
+                fail!("There is no report template '{name}'. Available: {names}")
+            }
+        };
+        match toml::from_str(&text) {
+            Ok(template) => template,
+            Err(e) => {
+                let path = path.display();
+                fail!("Could not parse {path}: {e}")
+            }
+        }
+    }
+
+    /// Lays out `entries`, written between `since` and `until`, in groups
+    pub fn render(
+        &self,
+        entries: &[DiaryEntry],
+        grouping: Grouping,
+        since: NaiveDate,
+        until: NaiveDate,
+    ) -> String {
+        let mut groups: BTreeMap<(String, String), Vec<&DiaryEntry>> = BTreeMap::new();
+        for entry in entries {
+            for key in grouping.keys(entry) {
+                groups.entry(key).or_default().push(entry);
+            }
+        }
+
+        let mut blocks = Vec::new();
+        let header = fill(
+            &self.header,
+            &[
+                ("since", since.to_string()),
+                ("until", until.to_string()),
+                ("count", entries.len().to_string()),
+            ],
+        );
+        if !header.is_empty() {
+            blocks.push(header);
+        }
+        for ((_, name), mut entries) in groups {
+            entries.sort();
+            let mut lines = vec![fill(
+                &self.group,
+                &[("group", name), ("count", entries.len().to_string())],
+            )];
+            lines.extend(entries.iter().map(|entry| self.show_entry(entry)));
+            lines.retain(|line| !line.is_empty());
+            blocks.push(lines.join("\n"));
+        }
+
+        let (tags, repos) = sessions::totals(entries);
+        for (name, totals) in [("Time by tag", tags), ("Time by repo", repos)] {
+            if totals.is_empty() {
+                continue;
+            }
+            let mut lines = vec![fill(
+                &self.group,
+                &[
+                    ("group", String::from(name)),
+                    ("count", totals.len().to_string()),
+                ],
+            )];
+            lines.extend(totals.into_iter().map(|(name, duration)| {
+                fill(
+                    &self.total,
+                    &[("name", name), ("duration", show_duration(duration))],
+                )
+            }));
+            lines.retain(|line| !line.is_empty());
+            blocks.push(lines.join("\n"));
+        }
+        blocks.join("\n\n")
+    }
+
+    fn show_entry(&self, entry: &DiaryEntry) -> String {
+        let datetime = entry.date.to_datetime();
+        let content = entry.content.trim();
+        let tags: String = entry.tags.iter().map(|tag| format!(" #{tag}")).collect();
+        let attachments = match entry.attachments.is_empty() {
+            true => String::new(),
+            false => {
+                let files: Vec<String> = entry
+                    .attachments
+                    .iter()
+                    .map(|file| format!("{} ({})", file.name, show_size(file.size)))
+                    .collect();
+                format!(" (attached: {})", files.join(", "))
+            }
+        };
+        fill(
+            &self.entry,
+            &[
+                ("id", entry.id.to_string()),
+                (
+                    "title",
+                    entry
+                        .title
+                        .clone()
+                        .unwrap_or_else(|| String::from("Untitled")),
+                ),
+                ("content", content.to_string()),
+                ("summary", content.lines().next().unwrap_or("").to_string()),
+                (
+                    "date",
+                    datetime.map_or_else(
+                        || entry.date.display(),
+                        |d| d.format("%Y-%m-%d").to_string(),
+                    ),
+                ),
+                (
+                    "time",
+                    datetime.map_or_else(String::new, |d| d.format("%H:%M").to_string()),
+                ),
+                ("tags", tags),
+                ("repo", entry.repo.clone().unwrap_or_default()),
+                ("notebook", entry.notebook.clone()),
+                (
+                    "duration",
+                    entry.duration().map(show_duration).unwrap_or_default(),
+                ),
+                ("attachments", attachments),
+            ],
+        )
+    }
+}
+
+fn template_path(name: &str) -> PathBuf {
+    config_dir().join("reports").join(format!("{name}.toml"))
+}
+
+/// The names of the built-in templates and the user's own
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = BUILT_IN.iter().map(|name| name.to_string()).collect();
+    names.extend(stems(&config_dir().join("reports"), "toml"));
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attachments::Attachment;
+    use crate::timestamps::TimeStamp;
+
+    fn date(day: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap()
+    }
+
+    fn entry(id: u64, date: &str, title: &str, tags: &[&str]) -> DiaryEntry {
+        DiaryEntry {
+            id,
+            title: Some(title.to_string()),
+            content: format!("Worked on {title}\nand more"),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..DiaryEntry::at(date)
+        }
+    }
+
+    #[test]
+    fn markdown_groups_by_day_oldest_first() {
+        let mut entries = [
+            entry(2, "2022-03-02 09:00:00", "parser", &["work"]),
+            entry(1, "2022-03-01 17:00:00", "lexer", &[]),
+        ];
+        entries[0].attachments = vec![Attachment {
+            name: String::from("trace.log"),
+            hash: String::new(),
+            size: 2048,
+        }];
+        let report = Template::markdown().render(
+            &entries,
+            Grouping::Day,
+            date("2022-03-01"),
+            date("2022-03-02"),
+        );
+        assert_eq!(
+            report,
+            "# Report for 2022-03-01 to 2022-03-02\n\
+             \n\
+             ## Tuesday 2022-03-01\n\
+             - **lexer**: Worked on lexer\n  and more\n\
+             \n\
+             ## Wednesday 2022-03-02\n\
+             - **parser** #work: Worked on parser\n  and more (attached: trace.log (2.0 KB))"
+        );
+    }
+
+    #[test]
+    fn tags_group_entries_under_each_tag_and_untagged_last() {
+        let entries = [
+            entry(1, "2022-03-01 09:00:00", "lexer", &["work", "rust"]),
+            entry(2, "2022-03-01 10:00:00", "lunch", &[]),
+            entry(3, "2022-03-01 11:00:00", "parser", &["rust"]),
+        ];
+        let template = Template {
+            header: String::new(),
+            group: String::from("{{group}} ({{count}})"),
+            entry: String::from("{{id}} {{summary}} at {{time}}"),
+            total: String::new(),
+        };
+        let report = template.render(
+            &entries,
+            Grouping::Tag,
+            date("2022-03-01"),
+            date("2022-03-01"),
+        );
+        assert_eq!(
+            report,
+            "#rust (2)\n1 Worked on lexer at 09:00\n3 Worked on parser at 11:00\n\
+             \n\
+             #work (1)\n1 Worked on lexer at 09:00\n\
+             \n\
+             Untagged (1)\n2 Worked on lunch at 10:00"
+        );
+    }
+
+    #[test]
+    fn repos_group_by_name_and_entries_without_one_last() {
+        let mut entries = [
+            entry(1, "2022-03-01 09:00:00", "lexer", &[]),
+            entry(2, "2022-03-01 10:00:00", "lunch", &[]),
+        ];
+        entries[0].repo = Some(String::from("code_diary"));
+        let report = Template::text().render(
+            &entries,
+            Grouping::Repo,
+            date("2022-03-01"),
+            date("2022-03-01"),
+        );
+        assert_eq!(
+            report,
+            "Report for 2022-03-01 to 2022-03-01\n\
+             \n\
+             code_diary\n  * lexer: Worked on lexer\n    and more\n\
+             \n\
+             No repository\n  * lunch: Worked on lunch\n    and more"
+        );
+    }
+
+    #[test]
+    fn tracked_time_is_totalled_after_the_groups() {
+        let mut session = entry(1, "2022-03-01 09:00:00", "lexer", &["work"]);
+        session.ended = TimeStamp::from_string("2022-03-01 10:30:00");
+        session.repo = Some(String::from("code_diary"));
+        let template = Template {
+            entry: String::from("- {{title}} {{duration}}"),
+            ..Template::text()
+        };
+        let report = template.render(
+            &[session],
+            Grouping::Repo,
+            date("2022-03-01"),
+            date("2022-03-01"),
+        );
+        assert_eq!(
+            report,
+            "Report for 2022-03-01 to 2022-03-01\n\
+             \n\
+             code_diary\n- lexer 1h 30m\n\
+             \n\
+             Time by tag\n  #work: 1h 30m\n\
+             \n\
+             Time by repo\n  code_diary: 1h 30m"
+        );
+    }
+}
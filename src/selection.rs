@@ -0,0 +1,205 @@
+//! Selecting several entries at once, by id, by range of ids, by tag and by
+//! date. Used by the commands that act on many entries, like `delete` and
+//! `tag`.
+
+use chrono::Datelike;
+
+use crate::diaryentry::DiaryEntry;
+use crate::fail;
+use crate::timestamps::TimeStamp;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Which entries to act on. An entry is selected if it matches every
+/// criterion that is set.
+pub struct Selection {
+    /// Inclusive ranges of entry ids, a single id being a range of one; if
+    /// empty, entries are not filtered by id
+    pub ids: Vec<(u64, u64)>,
+    /// Only entries carrying this tag
+    pub tag: Option<String>,
+    /// Only entries written before this time
+    pub before: Option<TimeStamp>,
+    /// Only entries written at or after this time
+    pub since: Option<TimeStamp>,
+}
+
+impl Selection {
+    /// Builds a selection from command line arguments: `ids` may hold single
+    /// ids (`5`) or inclusive ranges (`10-14`), and the dates are anything
+    /// [`parse_date`] understands.
+    /// # Panics
+    /// Will 'gracefully' exit if an id, range or date is malformed
+    pub fn from_args(
+        ids: &[String],
+        tag: Option<String>,
+        before: Option<&str>,
+        since: Option<&str>,
+    ) -> Self {
+        Self {
+            ids: parse_ids(ids),
+            tag,
+            before: before.map(parse_date),
+            since: since.map(parse_date),
+        }
+    }
+
+    /// Returns whether nothing has been specified, in which case the user
+    /// should be asked interactively.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty() && self.tag.is_none() && self.before.is_none() && self.since.is_none()
+    }
+
+    /// Returns whether `entry` is selected
+    pub fn matches(&self, entry: &DiaryEntry) -> bool {
+        (self.ids.is_empty() || self.contains_id(entry.id))
+            && self.tag.as_ref().is_none_or(|tag| entry.tags.contains(tag))
+            && self.contains_date(&entry.date)
+    }
+
+    /// Returns whether `id` is within one of the ranges of ids
+    pub fn contains_id(&self, id: u64) -> bool {
+        self.ids
+            .iter()
+            .any(|&(start, end)| (start..=end).contains(&id))
+    }
+
+    /// Returns whether `date` is within the date range
+    pub fn contains_date(&self, date: &TimeStamp) -> bool {
+        self.before.is_none_or(|before| *date < before)
+            && self.since.is_none_or(|since| *date >= since)
+    }
+
+    /// Returns the selected entries, keeping their order
+    pub fn apply(&self, entries: Vec<DiaryEntry>) -> Vec<DiaryEntry> {
+        entries
+            .into_iter()
+            .filter(|entry| self.matches(entry))
+            .collect()
+    }
+}
+
+/// Parses ids and inclusive ranges of ids like `3`, `10-14` into ranges, a
+/// single id being a range of one
+/// # Panics
+/// Will 'gracefully' exit if an argument is neither
+pub fn parse_ids(args: &[String]) -> Vec<(u64, u64)> {
+    let mut ids = Vec::new();
+    for arg in args {
+        let parsed = match arg.split_once('-') {
+            Some((start, end)) => start.parse::<u64>().ok().zip(end.parse::<u64>().ok()),
+            None => arg.parse::<u64>().ok().map(|id| (id, id)),
+        };
+        match parsed {
+            Some((start, end)) if start <= end => ids.push((start, end)),
+            _ => fail!("'{arg}' is not an id or a range of ids like 10-14"),
+        }
+    }
+    ids
+}
+
+/// Parses a date given as `YYYY-MM-DD`, `today`, `yesterday`, or a number of
+/// days or weeks ago like `3d` or `2w`, into the start of that day
+/// # Panics
+/// Will 'gracefully' exit if `date` is none of those
+pub fn parse_date(date: &str) -> TimeStamp {
+    match parse_day(date) {
+        Some(date) => TimeStamp::start_of(date),
+        None => fail!("'{date}' is not a date like 2023-01-31, today, yesterday, 3d or 2w"),
+    }
+}
+
+/// The day `date` names, or `None` if it names none or one whose year a
+/// `TimeStamp` can't hold
+fn parse_day(date: &str) -> Option<chrono::NaiveDate> {
+    let today = chrono::Local::now().date_naive();
+    let days_ago = |count: &str, unit: u32| {
+        let days = count.parse::<u32>().ok()?.checked_mul(unit)?;
+        today.checked_sub_signed(chrono::Duration::try_days(days.into())?)
+    };
+    let parsed = match date {
+        "today" => Some(today),
+        "yesterday" => today.pred_opt(),
+        _ => match (date.strip_suffix('d'), date.strip_suffix('w')) {
+            (Some(count), _) => days_ago(count, 1),
+            (_, Some(count)) => days_ago(count, 7),
+            _ => chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok(),
+        },
+    }?;
+    u16::try_from(parsed.year()).is_ok().then_some(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_ids_keeps_ranges_whole() {
+        assert_eq!(
+            parse_ids(&args(&["3", "10-14", "7-7"])),
+            vec![(3, 3), (10, 14), (7, 7)]
+        );
+        assert_eq!(parse_ids(&args(&["0-18446744073709551615"])).len(), 1);
+    }
+
+    #[test]
+    fn ids_match_within_any_range() {
+        let selection = Selection::from_args(&args(&["3", "10-14"]), None, None, None);
+        let ids: Vec<u64> = (0..20).filter(|&id| selection.contains_id(id)).collect();
+        assert_eq!(ids, vec![3, 10, 11, 12, 13, 14]);
+    }
+
+    #[test]
+    fn parse_date_accepts_dates_and_relative_days() {
+        let today = chrono::Local::now().date_naive();
+        let start = |date: NaiveDate| TimeStamp::start_of(date);
+        assert_eq!(
+            parse_date("2023-01-31"),
+            start(NaiveDate::from_ymd_opt(2023, 1, 31).unwrap())
+        );
+        assert_eq!(parse_date("today"), start(today));
+        assert_eq!(parse_date("yesterday"), start(today.pred_opt().unwrap()));
+        assert_eq!(parse_date("3d"), start(today - chrono::Duration::days(3)));
+        assert_eq!(parse_date("2w"), start(today - chrono::Duration::days(14)));
+        assert_eq!(parse_day("-3d"), None);
+        assert_eq!(parse_day("99999999999999d"), None);
+        assert_eq!(parse_day("4294967295w"), None);
+        assert_eq!(parse_day("1000000d"), None);
+        assert_eq!(parse_day("-0001-01-01"), None);
+    }
+
+    #[test]
+    fn every_criterion_must_match() {
+        let selection = Selection::from_args(
+            &args(&["1-3"]),
+            Some(String::from("scratch")),
+            Some("2023-01-01"),
+            Some("2022-06-01"),
+        );
+        let entry = |id, date, tags: &[&str]| DiaryEntry {
+            id,
+            tags: args(tags),
+            ..DiaryEntry::at(date)
+        };
+        let entries = vec![
+            entry(1, "2022-07-01 12:00:00", &["scratch"]),
+            entry(2, "2022-07-01 12:00:00", &["keep"]),
+            entry(3, "2023-01-01 00:00:00", &["scratch"]),
+            entry(4, "2022-07-01 12:00:00", &["scratch"]),
+            entry(5, "2022-05-31 23:59:59", &["scratch"]),
+        ];
+        let ids: Vec<u64> = selection.apply(entries).iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![1]);
+    }
+
+    #[test]
+    fn nothing_given_is_empty() {
+        assert!(Selection::from_args(&[], None, None, None).is_empty());
+        assert!(!Selection::from_args(&args(&["1"]), None, None, None).is_empty());
+    }
+}
@@ -0,0 +1,179 @@
+//! Time tracking with `code_diary start` and `code_diary stop`. Starting a
+//! session remembers what is being worked on; stopping it asks for notes and
+//! records an entry written at the start of the session that also knows when
+//! it ended, so `report` and `stats` can total the time spent.
+
+use std::collections::HashMap;
+
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::backend::{get_setting, remove_setting, set_setting};
+use crate::diaryentry::DiaryEntry;
+use crate::timestamps::TimeStamp;
+use crate::{crypto, fail};
+
+/// The settings key holding the running session
+const RUNNING: &str = "RunningSession";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// A session that has been started but not stopped
+pub struct Session {
+    /// What is being worked on
+    pub title: Option<String>,
+    /// The tags the entry will get
+    pub tags: Vec<String>,
+    /// The notebook the entry will go to
+    pub notebook: String,
+    /// The repository the session was started in
+    pub repo: Option<String>,
+    /// When the session started, as stored in the database
+    pub start: String,
+}
+
+impl Session {
+    /// Returns when the session started
+    pub fn start(&self) -> TimeStamp {
+        match TimeStamp::from_string(&self.start) {
+            Some(start) => start,
+            None => fail!("The running session has a malformed start time"),
+        }
+    }
+
+    /// Remembers the session as the running one. The title is sealed like
+    /// entry titles when the diary is encrypted.
+    pub fn save(&self) {
+        let sealed = Self {
+            title: self
+                .title
+                .as_deref()
+                .map(|title| crypto::seal(crypto::cipher(), title)),
+            ..self.clone()
+        };
+        set_setting(RUNNING, &toml::to_string(&sealed).unwrap());
+    }
+
+    /// Returns the running session, if there is one
+    /// # Panics
+    /// Will 'gracefully' exit if it cannot be read
+    pub fn running() -> Option<Self> {
+        let session: Self = match toml::from_str(&get_setting(RUNNING)?) {
+            Ok(session) => session,
+            Err(e) => fail!("Could not read the running session: {e}"),
+        };
+        Some(Self {
+            title: session
+                .title
+                .as_deref()
+                .map(|title| crypto::open(crypto::cipher(), title)),
+            ..session
+        })
+    }
+
+    /// Forgets the running session
+    pub fn clear() {
+        remove_setting(RUNNING);
+    }
+}
+
+/// Formats `duration` like `2h 05m` or `25m`
+pub fn show_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    match minutes / 60 {
+        0 => format!("{minutes}m"),
+        hours => format!("{hours}h {:02}m", minutes % 60),
+    }
+}
+
+/// The time tracked on each tag or repository, most time first
+pub type Totals = Vec<(String, Duration)>;
+
+/// Totals the time tracked in `entries`, by tag and by repository, most time
+/// first. Entries without a session are left out.
+pub fn totals(entries: &[DiaryEntry]) -> (Totals, Totals) {
+    let mut tags: HashMap<String, Duration> = HashMap::new();
+    let mut repos: HashMap<String, Duration> = HashMap::new();
+    for entry in entries {
+        let Some(duration) = entry.duration() else {
+            continue;
+        };
+        for tag in &entry.tags {
+            add(&mut tags, format!("#{tag}"), duration);
+        }
+        let repo = entry
+            .repo
+            .clone()
+            .unwrap_or_else(|| String::from("No repository"));
+        add(&mut repos, repo, duration);
+    }
+    (sorted(tags), sorted(repos))
+}
+
+fn add(totals: &mut HashMap<String, Duration>, key: String, duration: Duration) {
+    let total = totals.entry(key).or_insert_with(Duration::zero);
+    *total += duration;
+}
+
+fn sorted(totals: HashMap<String, Duration>) -> Totals {
+    let mut totals: Totals = totals.into_iter().collect();
+    totals.sort_by(|(a, a_time), (b, b_time)| b_time.cmp(a_time).then(a.cmp(b)));
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(start: &str, minutes: i64, tags: &[&str], repo: Option<&str>) -> DiaryEntry {
+        let entry = DiaryEntry::at(start);
+        let ended = entry.date.to_datetime().unwrap() + Duration::minutes(minutes);
+        DiaryEntry {
+            ended: TimeStamp::from_string(&ended.format("%Y-%m-%d %H:%M:%S").to_string()),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            repo: repo.map(String::from),
+            ..entry
+        }
+    }
+
+    #[test]
+    fn show_duration_rounds_down_to_minutes() {
+        assert_eq!(show_duration(Duration::seconds(59)), "0m");
+        assert_eq!(show_duration(Duration::minutes(25)), "25m");
+        assert_eq!(show_duration(Duration::minutes(125)), "2h 05m");
+        assert_eq!(show_duration(Duration::minutes(-5)), "0m");
+    }
+
+    #[test]
+    fn totals_add_up_by_tag_and_repo_most_first() {
+        let entries = [
+            session(
+                "2022-03-01 09:00:00",
+                30,
+                &["bug", "ci"],
+                Some("code_diary"),
+            ),
+            session("2022-03-01 10:00:00", 90, &["bug"], None),
+            session("2022-03-01 12:00:00", 30, &[], Some("code_diary")),
+            // Not a session, so not counted
+            DiaryEntry {
+                tags: vec![String::from("ci")],
+                ..DiaryEntry::at("2022-03-01 13:00:00")
+            },
+        ];
+        let (tags, repos) = totals(&entries);
+        assert_eq!(
+            tags,
+            vec![
+                (String::from("#bug"), Duration::minutes(120)),
+                (String::from("#ci"), Duration::minutes(30)),
+            ]
+        );
+        assert_eq!(
+            repos,
+            vec![
+                (String::from("No repository"), Duration::minutes(90)),
+                (String::from("code_diary"), Duration::minutes(60)),
+            ]
+        );
+    }
+}
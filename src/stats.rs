@@ -0,0 +1,413 @@
+//! Statistics about the entries in a notebook, shown by `code_diary stats`:
+//! how much and how regularly the user writes, and a contribution heatmap of
+//! the past year. With `--field`, one of the user's ratings is charted over
+//! time instead.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{Datelike, Duration, NaiveDate, Timelike};
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::diaryentry::DiaryEntry;
+use crate::fields;
+use crate::sessions::{self, show_duration, Totals};
+use crate::theme::{paint, Role};
+
+/// How many of the busiest hours are shown
+const BUSIEST_HOURS: usize = 3;
+/// The longest bar in the busiest hours chart
+const BAR_WIDTH: usize = 20;
+/// At most a year of weeks fits in the heatmap
+const HEATMAP_WEEKS: usize = 53;
+/// The heatmap cells from no entries to the most entries on a day
+const HEAT: [&str; 5] = ["·", "░", "▒", "▓", "█"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// What `stats` shows, computed by [`Stats::compute`]
+pub struct Stats {
+    /// The number of entries
+    pub total: usize,
+    /// The average number of entries per week since the first entry
+    pub per_week: f64,
+    /// The average number of entries per month since the first entry
+    pub per_month: f64,
+    /// The average number of words in an entry
+    pub average_words: f64,
+    /// The longest run of consecutive days with an entry, and the day it ended
+    pub longest_streak: (usize, Option<NaiveDate>),
+    /// The run of consecutive days with an entry up to today. Today counts
+    /// even before the first entry of the day is written.
+    pub current_streak: usize,
+    /// The hours of the day entries are written in, busiest first
+    pub busiest_hours: Vec<(u32, usize)>,
+    /// How many entries were written on each day
+    pub days: BTreeMap<NaiveDate, usize>,
+    /// The time tracked with `start` and `stop` on each tag, most first
+    pub time_by_tag: Totals,
+    /// The time tracked with `start` and `stop` in each repository, most
+    /// first
+    pub time_by_repo: Totals,
+}
+
+impl Stats {
+    /// Computes the statistics for `entries` as of `today`. Entries whose
+    /// timestamps are not real dates are only counted in the total.
+    pub fn compute(entries: &[DiaryEntry], today: NaiveDate) -> Self {
+        let datetimes: Vec<_> = entries
+            .iter()
+            .filter_map(|entry| entry.date.to_datetime())
+            .collect();
+
+        let mut days = BTreeMap::new();
+        let mut hours = [0usize; 24];
+        for datetime in &datetimes {
+            *days.entry(datetime.date()).or_insert(0) += 1;
+            hours[datetime.hour() as usize] += 1;
+        }
+
+        let span_days = match days.keys().next() {
+            Some(first) => (today - *first).num_days().max(0) as f64 + 1.0,
+            None => 1.0,
+        };
+        let words: usize = entries
+            .iter()
+            .map(|entry| entry.content.split_whitespace().count())
+            .sum();
+
+        let mut busiest_hours: Vec<(u32, usize)> = (0..24)
+            .map(|hour| (hour, hours[hour as usize]))
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        busiest_hours.sort_by_key(|&(hour, count)| (std::cmp::Reverse(count), hour));
+        busiest_hours.truncate(BUSIEST_HOURS);
+
+        let written: BTreeSet<NaiveDate> = days.keys().copied().collect();
+        let (time_by_tag, time_by_repo) = sessions::totals(entries);
+        Self {
+            total: entries.len(),
+            per_week: datetimes.len() as f64 / (span_days / 7.0).max(1.0),
+            per_month: datetimes.len() as f64 / (span_days / 30.44).max(1.0),
+            average_words: match entries.len() {
+                0 => 0.0,
+                count => words as f64 / count as f64,
+            },
+            longest_streak: longest_streak(&written),
+            current_streak: current_streak(&written, today),
+            busiest_hours,
+            days,
+            time_by_tag,
+            time_by_repo,
+        }
+    }
+
+    /// Formats the statistics for the terminal, with a heatmap at most `width`
+    /// columns wide ending at `today`
+    pub fn show(&self, today: NaiveDate, width: usize) -> String {
+        let number = |value: String| paint(Role::Accent, &value);
+        let days = |count: usize| match count {
+            1 => String::from("1 day"),
+            count => format!("{count} days"),
+        };
+
+        let mut lines = vec![
+            format!("Entries         {}", number(self.total.to_string())),
+            format!(
+                "Per week        {}",
+                number(format!("{:.1}", self.per_week))
+            ),
+            format!(
+                "Per month       {}",
+                number(format!("{:.1}", self.per_month))
+            ),
+            format!(
+                "Average length  {} words",
+                number(format!("{:.0}", self.average_words))
+            ),
+        ];
+        let longest = match self.longest_streak {
+            (length, Some(end)) if length > 0 => {
+                let start = end - Duration::days(length as i64 - 1);
+                format!(
+                    "{} {}",
+                    number(days(length)),
+                    paint(Role::Muted, &format!("({start} to {end})"))
+                )
+            }
+            _ => number(days(0)),
+        };
+        lines.push(format!("Longest streak  {longest}"));
+        lines.push(format!(
+            "Current streak  {}",
+            number(days(self.current_streak))
+        ));
+
+        if !self.busiest_hours.is_empty() {
+            lines.push(String::new());
+            lines.push(paint(Role::Heading, "Busiest hours"));
+            let most = self.busiest_hours[0].1;
+            for &(hour, count) in &self.busiest_hours {
+                let bar = "█".repeat((count * BAR_WIDTH).div_ceil(most));
+                lines.push(format!(
+                    "  {hour:02}:00  {} {count}",
+                    paint(Role::Accent, &bar)
+                ));
+            }
+        }
+
+        for (heading, totals) in [
+            ("Time by tag", &self.time_by_tag),
+            ("Time by repo", &self.time_by_repo),
+        ] {
+            if totals.is_empty() {
+                continue;
+            }
+            lines.push(String::new());
+            lines.push(paint(Role::Heading, heading));
+            let width = totals
+                .iter()
+                .map(|(name, _)| name.width())
+                .max()
+                .unwrap_or(0);
+            for (name, duration) in totals {
+                let padding = " ".repeat(width - name.width());
+                lines.push(format!(
+                    "  {name}{padding}  {}",
+                    number(show_duration(*duration))
+                ));
+            }
+        }
+
+        lines.push(String::new());
+        lines.push(paint(Role::Heading, "Past year"));
+        lines.extend(self.heatmap(today, width));
+        lines.join("\n")
+    }
+
+    /// Draws a GitHub-style heatmap: a column per week, a row per weekday,
+    /// and a darker cell for more entries
+    fn heatmap(&self, today: NaiveDate, width: usize) -> Vec<String> {
+        // Four columns of weekday labels, then two per week
+        let weeks = (width.saturating_sub(4) / 2).clamp(1, HEATMAP_WEEKS);
+        let this_monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let first_monday = this_monday - Duration::weeks(weeks as i64 - 1);
+        let thresholds = quartiles(self.days.values().copied().collect());
+
+        let mut months = String::from("    ");
+        let mut column = 0;
+        for week in 0..weeks {
+            let monday = first_monday + Duration::weeks(week as i64);
+            let label = monday.format("%b").to_string();
+            let starts_month = monday.day() <= 7;
+            if starts_month && week * 2 >= column && week + 2 <= weeks {
+                months.push_str(&" ".repeat(week * 2 - column));
+                months.push_str(&label);
+                column = week * 2 + label.len();
+            }
+        }
+
+        let mut lines = vec![months.trim_end().to_string()];
+        for weekday in 0..7 {
+            let label = match weekday {
+                0 => "Mon ",
+                2 => "Wed ",
+                4 => "Fri ",
+                _ => "    ",
+            };
+            let mut line = String::from(label);
+            for week in 0..weeks {
+                let day = first_monday + Duration::days(week as i64 * 7 + weekday);
+                if day > today {
+                    break;
+                }
+                let count = self.days.get(&day).copied().unwrap_or(0);
+                line.push_str(&heat(count, &thresholds));
+                line.push(' ');
+            }
+            lines.push(line.trim_end().to_string());
+        }
+
+        let legend: Vec<String> = (0..HEAT.len()).map(heat_cell).collect();
+        lines.push(format!("    Less {} More", legend.join(" ")));
+        lines
+    }
+}
+
+/// Charts the ratings of `field` over time as a sparkline at most `width`
+/// columns wide, one bar per day it was rated, with a summary underneath.
+/// Returns `None` if no entry rates it.
+pub fn show_field(entries: &[DiaryEntry], field: &str, width: usize) -> Option<String> {
+    let days = fields::daily_averages(entries, field);
+    let (first, _) = days.first()?;
+    let (last, latest) = days.last()?;
+    let values: Vec<f64> = days.iter().map(|(_, value)| *value).collect();
+    let average = values.iter().sum::<f64>() / values.len() as f64;
+    let lowest = values.iter().copied().fold(f64::MAX, f64::min);
+    let highest = values.iter().copied().fold(f64::MIN, f64::max);
+    let shown = &values[values.len().saturating_sub(width.saturating_sub(2).max(1))..];
+
+    let number = |value: f64| paint(Role::Accent, &format!("{value:.1}"));
+    let days_rated = match days.len() {
+        1 => String::from("1 day"),
+        count => format!("{count} days"),
+    };
+    Some(
+        [
+            paint(Role::Heading, field),
+            format!("  {}", paint(Role::Accent, &fields::sparkline(shown))),
+            paint(
+                Role::Muted,
+                &format!("  {first} to {last}, {days_rated} rated"),
+            ),
+            String::new(),
+            format!("Average  {}", number(average)),
+            format!("Lowest   {}", number(lowest)),
+            format!("Highest  {}", number(highest)),
+            format!("Latest   {}", number(*latest)),
+        ]
+        .join("\n"),
+    )
+}
+
+/// The heatmap cell for a day with `count` entries. Levels are split at the
+/// quartiles of the busy days, so one unusually busy day does not wash out
+/// the rest.
+fn heat(count: usize, thresholds: &[usize]) -> String {
+    match count {
+        0 => heat_cell(0),
+        count => heat_cell(1 + thresholds.iter().filter(|&&t| count > t).count()),
+    }
+}
+
+/// The 25th, 50th and 75th percentile of `counts`
+fn quartiles(mut counts: Vec<usize>) -> Vec<usize> {
+    counts.sort_unstable();
+    match counts.is_empty() {
+        true => Vec::new(),
+        false => [1, 2, 3]
+            .iter()
+            .map(|quarter| counts[(counts.len() - 1) * quarter / 4])
+            .collect(),
+    }
+}
+
+fn heat_cell(level: usize) -> String {
+    match level {
+        0 => paint(Role::Muted, HEAT[0]),
+        level => paint(Role::Accent, HEAT[level.min(HEAT.len() - 1)]),
+    }
+}
+
+/// The longest run of consecutive days in `days`, and the day it ended
+fn longest_streak(days: &BTreeSet<NaiveDate>) -> (usize, Option<NaiveDate>) {
+    let mut longest = (0, None);
+    let mut current = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in days {
+        current = match previous {
+            Some(previous) if day - previous == Duration::days(1) => current + 1,
+            _ => 1,
+        };
+        if current > longest.0 {
+            longest = (current, Some(day));
+        }
+        previous = Some(day);
+    }
+    longest
+}
+
+/// The run of consecutive days in `days` ending today, or yesterday if
+/// nothing has been written yet today
+fn current_streak(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> usize {
+    let mut day = match days.contains(&today) {
+        true => today,
+        false => today - Duration::days(1),
+    };
+    let mut streak = 0;
+    while days.contains(&day) {
+        streak += 1;
+        day -= Duration::days(1);
+    }
+    streak
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamps::TimeStamp;
+
+    fn date(day: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap()
+    }
+
+    fn days(days: &[&str]) -> BTreeSet<NaiveDate> {
+        days.iter().map(|day| date(day)).collect()
+    }
+
+    #[test]
+    fn longest_streak_finds_the_longest_run_and_its_end() {
+        assert_eq!(longest_streak(&days(&[])), (0, None));
+        let written = days(&[
+            "2022-02-26",
+            "2022-02-27",
+            "2022-02-28",
+            "2022-03-01",
+            "2022-03-05",
+            "2022-03-06",
+        ]);
+        assert_eq!(longest_streak(&written), (4, Some(date("2022-03-01"))));
+        // The first of equally long runs wins
+        let written = days(&["2022-01-01", "2022-01-02", "2022-01-04", "2022-01-05"]);
+        assert_eq!(longest_streak(&written), (2, Some(date("2022-01-02"))));
+    }
+
+    #[test]
+    fn current_streak_counts_back_from_today_or_yesterday() {
+        let written = days(&["2022-03-01", "2022-03-03", "2022-03-04", "2022-03-05"]);
+        assert_eq!(current_streak(&written, date("2022-03-05")), 3);
+        assert_eq!(current_streak(&written, date("2022-03-06")), 3);
+        assert_eq!(current_streak(&written, date("2022-03-07")), 0);
+        assert_eq!(current_streak(&written, date("2022-03-02")), 1);
+    }
+
+    #[test]
+    fn quartiles_pick_from_the_sorted_counts() {
+        assert_eq!(quartiles(Vec::new()), Vec::<usize>::new());
+        assert_eq!(quartiles(vec![7]), vec![7, 7, 7]);
+        assert_eq!(quartiles(vec![5, 1, 4, 2, 3]), vec![2, 3, 4]);
+        assert_eq!(quartiles(vec![1, 1, 1, 1, 1, 1, 1, 40]), vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn compute_counts_only_real_dates_by_day() {
+        let entry = |date, content: &str| DiaryEntry {
+            content: content.to_string(),
+            ..DiaryEntry::at(date)
+        };
+        // As read from an old database, before timestamps were checked
+        let impossible = DiaryEntry {
+            date: TimeStamp {
+                year: 2022,
+                month: 2,
+                day: 30,
+                hour: 10,
+                minute: 0,
+                second: 0,
+            },
+            ..Default::default()
+        };
+        let entries = [
+            entry("2022-03-01 09:15:00", "one two"),
+            entry("2022-03-01 09:45:00", "three"),
+            entry("2022-03-02 17:00:00", "four five six"),
+            impossible,
+        ];
+        let stats = Stats::compute(&entries, date("2022-03-02"));
+        assert_eq!(stats.total, 4);
+        assert_eq!(stats.average_words, 1.5);
+        assert_eq!(stats.days.get(&date("2022-03-01")), Some(&2));
+        assert_eq!(stats.days.get(&date("2022-03-02")), Some(&1));
+        assert_eq!(stats.current_streak, 2);
+        assert_eq!(stats.busiest_hours, vec![(9, 2), (17, 1)]);
+    }
+}
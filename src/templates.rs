@@ -0,0 +1,328 @@
+//! Named entry templates for the shapes of entry written again and again, like
+//! bug investigations or retros, used with `code_diary add --template <name>`.
+//!
+//! Templates are Markdown files in the `templates` directory next to the
+//! configuration file, e.g. `~/.config/code_diary/templates/bug.md`. They may
+//! start with TOML front matter between `+++` lines giving a title and tags:
+//!
+//! ```text
+//! +++
+//! title = "Bug in {{repo}}"
+//! tags = ["bug"]
+//! +++
+//! ## Symptoms
+//! ```
+//!
+//! `{{date}}`, `{{time}}`, `{{repo}}`, `{{branch}}` and `{{notebook}}` are
+//! filled in when the template is used.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::backend::current_notebook;
+use crate::config::config_dir;
+use crate::{fail, git};
+
+/// Templates that exist without a file, as a starting point for `template new`
+const STARTERS: &[(&str, &str)] = &[
+    (
+        "bug",
+        "+++
+title = \"Bug in {{repo}}\"
+tags = [\"bug\"]
++++
+## Symptoms
+
+## Investigation
+
+Seen on `{{branch}}`.
+
+## Cause
+
+## Fix
+",
+    ),
+    (
+        "decision",
+        "+++
+title = \"Decision: \"
+tags = [\"decision\"]
++++
+## Context
+
+## Options
+
+## Decision
+
+## Consequences
+",
+    ),
+    (
+        "retro",
+        "+++
+title = \"Retro {{date}}\"
+tags = [\"retro\"]
++++
+## What went well
+
+## What did not
+
+## Actions
+",
+    ),
+    (
+        "til",
+        "+++
+title = \"TIL: \"
+tags = [\"til\"]
++++
+",
+    ),
+];
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+struct FrontMatter {
+    title: Option<String>,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// A template for a new entry
+pub struct EntryTemplate {
+    /// The title the entry starts with
+    pub title: Option<String>,
+    /// The tags the entry gets
+    pub tags: Vec<String>,
+    /// The content the entry starts with
+    pub body: String,
+}
+
+impl EntryTemplate {
+    /// Loads the template called `name`, from its file if there is one and
+    /// otherwise from the starters, with its placeholders filled in
+    /// # Panics
+    /// Will 'gracefully' exit if there is no such template or its front matter
+    /// is malformed
+    pub fn load(name: &str) -> Self {
+        let path = path(name);
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => match starter(name) {
+                Some(text) => text.to_string(),
+                None => {
+                    let names: Vec<String> = names().into_iter().map(|(name, _)| name).collect();
+                    let names = names.join(", ");
+                    fail!("There is no template '{name}'. Available: {names}")
+                }
+            },
+        };
+        Self::parse(&text, &path).fill()
+    }
+
+    fn parse(text: &str, path: &Path) -> Self {
+        let (front, body) = match text.strip_prefix("+++\n") {
+            Some(rest) => match rest.split_once("\n+++") {
+                Some((front, body)) => (front, body.strip_prefix('\n').unwrap_or(body)),
+                None => ("", text),
+            },
+            None => ("", text),
+        };
+        let front: FrontMatter = match toml::from_str(front) {
+            Ok(front) => front,
+            Err(e) => {
+                let path = path.display();
+                fail!("Could not parse the front matter of {path}: {e}")
+            }
+        };
+        Self {
+            title: front.title,
+            tags: front.tags,
+            body: body.trim_start_matches('\n').to_string(),
+        }
+    }
+
+    /// Fills in the placeholders with the current date and time, repository,
+    /// branch and notebook
+    fn fill(self) -> Self {
+        let now = chrono::Local::now();
+        let values = [
+            ("date", now.format("%Y-%m-%d").to_string()),
+            ("time", now.format("%H:%M").to_string()),
+            ("repo", git::repo_name().unwrap_or_default()),
+            ("branch", git::branch().unwrap_or_default()),
+            ("notebook", current_notebook()),
+        ];
+        Self {
+            title: self
+                .title
+                .map(|title| fill(&title, &values))
+                .filter(|title| !title.is_empty()),
+            tags: self.tags,
+            body: fill(&self.body, &values),
+        }
+    }
+}
+
+/// Returns the path of the template file called `name`
+/// # Panics
+/// Will 'gracefully' exit if `name` is not a plain file name
+pub fn path(name: &str) -> PathBuf {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        fail!("'{name}' is not a valid template name; use letters, digits, - and _");
+    }
+    config_dir().join("templates").join(format!("{name}.md"))
+}
+
+/// Returns the built-in starting point for a template called `name`, if there
+/// is one
+pub fn starter(name: &str) -> Option<&'static str> {
+    STARTERS
+        .iter()
+        .find(|(starter, _)| *starter == name)
+        .map(|(_, text)| *text)
+}
+
+/// The names of every template, and whether each is a built-in starter that
+/// has no file yet
+pub fn names() -> Vec<(String, bool)> {
+    let own = stems(&config_dir().join("templates"), "md");
+    let mut names: Vec<(String, bool)> = STARTERS
+        .iter()
+        .filter(|(name, _)| !own.iter().any(|own| own == name))
+        .map(|(name, _)| (name.to_string(), true))
+        .collect();
+    names.extend(own.into_iter().map(|name| (name, false)));
+    names.sort();
+    names
+}
+
+/// The names of the files in `dir` with the given extension, without it,
+/// sorted
+pub fn stems(dir: &Path, extension: &str) -> Vec<String> {
+    let mut stems: Vec<String> = match std::fs::read_dir(dir) {
+        Ok(dir) => dir
+            .filter_map(|file| file.ok())
+            .filter_map(|file| {
+                let path = file.path();
+                match path.extension()?.to_str()? == extension {
+                    true => Some(path.file_stem()?.to_string_lossy().into_owned()),
+                    false => None,
+                }
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    stems.sort();
+    stems
+}
+
+/// Replaces each `{{name}}` in `template` with its value. Values spanning
+/// several lines are indented to line up under the line they start on, so
+/// they stay inside list items.
+pub fn fill(template: &str, values: &[(&str, String)]) -> String {
+    let mut out = Vec::new();
+    for line in template.lines() {
+        let indent = format!("\n{}", " ".repeat(continuation_indent(line)));
+        let mut filled = String::new();
+        let mut rest = line;
+        while let Some(start) = rest.find("{{") {
+            let (before, after) = rest.split_at(start);
+            filled.push_str(before);
+            let value = after[2..].split_once("}}").and_then(|(name, tail)| {
+                let (_, value) = values.iter().find(|(key, _)| *key == name.trim())?;
+                Some((value, tail))
+            });
+            match value {
+                Some((value, tail)) => {
+                    filled.push_str(&value.replace('\n', &indent));
+                    rest = tail;
+                }
+                None => {
+                    filled.push_str("{{");
+                    rest = &after[2..];
+                }
+            }
+        }
+        filled.push_str(rest);
+        out.extend(filled.lines().map(|line| line.trim_end().to_string()));
+    }
+    out.join("\n").trim_end().to_string()
+}
+
+/// How far the lines after the first of a multi-line value are indented: as
+/// far as the line's own indentation and list marker, if it has one
+fn continuation_indent(line: &str) -> usize {
+    let text = line.trim_start();
+    let indent = line.len() - text.len();
+    match ["- ", "* ", "+ "]
+        .iter()
+        .any(|marker| text.starts_with(marker))
+    {
+        true => indent + 2,
+        false => indent,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> Vec<(&'static str, String)> {
+        vec![
+            ("repo", String::from("code_diary")),
+            ("notes", String::from("first\nsecond")),
+        ]
+    }
+
+    #[test]
+    fn parse_splits_off_the_front_matter() {
+        let text = "+++\ntitle = \"Bug in {{repo}}\"\ntags = [\"bug\"]\n+++\n\n## Symptoms\n";
+        assert_eq!(
+            EntryTemplate::parse(text, Path::new("bug.md")),
+            EntryTemplate {
+                title: Some(String::from("Bug in {{repo}}")),
+                tags: vec![String::from("bug")],
+                body: String::from("## Symptoms\n"),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_keeps_text_without_front_matter_whole() {
+        for text in ["## Notes\n", "+++\ntitle = \"Never closed\"\n"] {
+            let template = EntryTemplate::parse(text, Path::new("notes.md"));
+            assert_eq!(template.title, None);
+            assert_eq!(template.body, text);
+        }
+    }
+
+    #[test]
+    fn every_starter_parses() {
+        for (name, text) in STARTERS {
+            let template = EntryTemplate::parse(text, Path::new(name));
+            assert_eq!(template.tags, vec![name.to_string()]);
+            assert!(!template.body.starts_with("+++"));
+        }
+    }
+
+    #[test]
+    fn fill_replaces_known_placeholders_only() {
+        assert_eq!(
+            fill("Bug in {{ repo }} on {{branch}}", &values()),
+            "Bug in code_diary on {{branch}}"
+        );
+        assert_eq!(fill("{{repo", &values()), "{{repo");
+    }
+
+    #[test]
+    fn fill_lines_up_multi_line_values() {
+        assert_eq!(fill("Notes: {{notes}}", &values()), "Notes: first\nsecond");
+        assert_eq!(fill("  - {{notes}}\n", &values()), "  - first\n    second");
+    }
+}
@@ -0,0 +1,208 @@
+//! Colors and text styles for everything code_diary prints.
+//!
+//! Output is styled by [`Role`] rather than by color, and the selected
+//! [`Theme`] decides what each role looks like. Styling is turned off entirely
+//! with `--color never`, with `NO_COLOR`, or when stdout is not a terminal.
+
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::fail;
+
+/// The names of the built-in themes
+pub const THEMES: &[&str] = &["dark", "light", "mono"];
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What a piece of text is, which decides how it is styled
+pub enum Role {
+    /// An entry's title
+    Title,
+    /// The placeholder for an entry without a title
+    Untitled,
+    /// An entry's date
+    Date,
+    /// Parentheses and other decoration
+    Punctuation,
+    /// An entry's tags
+    Tag,
+    /// An entry's content
+    Body,
+    /// Markdown headings
+    Heading,
+    /// Inline code
+    Code,
+    /// Links
+    Link,
+    /// Ids, rules, and other secondary text
+    Muted,
+    /// Highlighted values, e.g. in statistics
+    Accent,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The ANSI SGR codes used for each [`Role`]
+pub struct Theme {
+    /// The name of the theme
+    pub name: &'static str,
+    title: &'static str,
+    untitled: &'static str,
+    date: &'static str,
+    punctuation: &'static str,
+    tag: &'static str,
+    body: &'static str,
+    heading: &'static str,
+    code: &'static str,
+    link: &'static str,
+    muted: &'static str,
+    accent: &'static str,
+    /// The `syntect` theme for code blocks, if they should be highlighted
+    pub syntax: Option<&'static str>,
+}
+
+impl Theme {
+    /// Looks up a built-in theme by name
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self {
+                name: "dark",
+                title: "1;34",
+                untitled: "37",
+                date: "31",
+                punctuation: "35",
+                tag: "32",
+                body: "33",
+                heading: "1;35",
+                code: "36",
+                link: "4;34",
+                muted: "2",
+                accent: "1;32",
+                syntax: Some("base16-ocean.dark"),
+            }),
+            "light" => Some(Self {
+                name: "light",
+                title: "1;34",
+                untitled: "2",
+                date: "31",
+                punctuation: "35",
+                tag: "32",
+                body: "",
+                heading: "1;35",
+                code: "34",
+                link: "4;34",
+                muted: "2",
+                accent: "1;32",
+                syntax: Some("InspiredGitHub"),
+            }),
+            "mono" => Some(Self {
+                name: "mono",
+                title: "1",
+                untitled: "2",
+                date: "",
+                punctuation: "",
+                tag: "3",
+                body: "",
+                heading: "1",
+                code: "",
+                link: "4",
+                muted: "2",
+                accent: "1",
+                syntax: None,
+            }),
+            _ => None,
+        }
+    }
+
+    fn codes(&self, role: Role) -> &'static str {
+        match role {
+            Role::Title => self.title,
+            Role::Untitled => self.untitled,
+            Role::Date => self.date,
+            Role::Punctuation => self.punctuation,
+            Role::Tag => self.tag,
+            Role::Body => self.body,
+            Role::Heading => self.heading,
+            Role::Code => self.code,
+            Role::Link => self.link,
+            Role::Muted => self.muted,
+            Role::Accent => self.accent,
+        }
+    }
+}
+
+/// Selects the theme and decides whether to style output at all. `color` is
+/// the value of `--color`: `auto` (the default), `always` or `never`.
+/// # Panics
+/// Will 'gracefully' exit if the theme or `color` is not recognised
+pub fn init(name: &str, color: Option<&str>) {
+    let theme = match Theme::by_name(name) {
+        Some(theme) => theme,
+        None => fail!("Unknown theme '{name}', expected one of dark, light or mono"),
+    };
+    let enabled = match color.unwrap_or("auto") {
+        "always" => true,
+        "never" => false,
+        "auto" => {
+            std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                && std::io::stdout().is_terminal()
+        }
+        other => fail!("Unknown --color '{other}', expected auto, always or never"),
+    };
+    let _ = THEME.set(theme);
+    let _ = ENABLED.set(enabled);
+}
+
+/// Returns the selected theme, or the dark theme if none was selected
+pub fn current() -> &'static Theme {
+    THEME.get_or_init(|| Theme::by_name("dark").unwrap())
+}
+
+/// Returns whether output is styled
+pub fn enabled() -> bool {
+    *ENABLED.get_or_init(|| true)
+}
+
+/// Styles `text` as `role`
+pub fn paint(role: Role, text: &str) -> String {
+    sgr(&[current().codes(role)], text)
+}
+
+/// Returns the raw SGR codes for `role`, for combining with other attributes
+/// through [`sgr`]
+pub fn codes(role: Role) -> &'static str {
+    current().codes(role)
+}
+
+/// Returns what `role` looks like as a `ratatui` style, for the interactive
+/// pickers, or no style at all if styling is off
+pub fn style(role: Role) -> Style {
+    if !enabled() {
+        return Style::default();
+    }
+    codes(role)
+        .split(';')
+        .fold(Style::default(), |style, code| match code {
+            "1" => style.add_modifier(Modifier::BOLD),
+            "2" => style.add_modifier(Modifier::DIM),
+            "3" => style.add_modifier(Modifier::ITALIC),
+            "4" => style.add_modifier(Modifier::UNDERLINED),
+            _ => match code.parse::<u8>() {
+                Ok(color @ 30..=37) => style.fg(Color::Indexed(color - 30)),
+                _ => style,
+            },
+        })
+}
+
+/// Wraps `text` in the given SGR codes (e.g. `"1"` for bold), skipping empty
+/// ones, unless styling is off
+pub fn sgr(codes: &[&str], text: &str) -> String {
+    let codes: Vec<&str> = codes.iter().copied().filter(|c| !c.is_empty()).collect();
+    if codes.is_empty() || !enabled() {
+        return text.to_string();
+    }
+    format!("\x1b[{}m{text}\x1b[0m", codes.join(";"))
+}
@@ -1,8 +1,18 @@
 //! Contains the `TimeStamp` struct and it's logic
 
+use std::sync::OnceLock;
+
 use chrono::Datelike;
 use chrono::Timelike;
 
+static DISPLAY_FORMAT: OnceLock<String> = OnceLock::new();
+
+/// Sets the [`chrono` format string](https://docs.rs/chrono/latest/chrono/format/strftime/index.html)
+/// used by [`TimeStamp::display`], normally from the user's configuration.
+pub fn set_display_format(format: &str) {
+    let _ = DISPLAY_FORMAT.set(format.to_string());
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 /// # TimeStamp
 /// `timestamps::TimeStamp` is a simple struct for storing timestamps.
@@ -47,7 +57,7 @@ pub struct TimeStamp {
 
 impl TimeStamp {
     /// Takes the current time and returns a timestamp.
-
+    ///
     /// Example:
     /// ```rust
     /// use timestamps::TimeStamp;
@@ -67,18 +77,19 @@ impl TimeStamp {
         let second = dt.second() as u8;
 
         Self {
-            year: year,
-            month: month,
-            day: day,
-            hour: hour,
-            minute: minute,
-            second: second,
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
         }
     }
-    /// Takes a string and converts it into a timestamp
-    pub fn from_string<'a>(timestamp: &'a str) -> Option<Self> {
+    /// Takes a string and converts it into a timestamp, or `None` if it is
+    /// not a real date and time, e.g. `2023-02-30 10:00:00`
+    pub fn from_string(timestamp: &str) -> Option<Self> {
         let regex = regex::Regex::new(
-            "([0-9]{3,4})-([0-9]{1,2})-([0-9]{1,2}) ([0-9]{1,2}):([0-9]{1,2}):([0-9]{1,2})",
+            "^([0-9]{3,4})-([0-9]{1,2})-([0-9]{1,2}) ([0-9]{1,2}):([0-9]{1,2}):([0-9]{1,2})$",
         )
         .unwrap();
         if !regex.is_match(timestamp) {
@@ -97,14 +108,64 @@ impl TimeStamp {
         let minute: u8 = times[1].parse().unwrap();
         let second: u8 = times[2].parse().unwrap();
 
-        Some(Self {
-            year: year,
-            month: month,
-            day: day,
-            hour: hour,
-            minute: minute,
-            second: second,
-        })
+        let timestamp = Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        };
+        timestamp.to_datetime().map(|_| timestamp)
+    }
+}
+
+impl TimeStamp {
+    /// Converts the timestamp into a `chrono::NaiveDateTime`, or `None` if it
+    /// does not describe a real date and time.
+    pub fn to_datetime(&self) -> Option<chrono::NaiveDateTime> {
+        chrono::NaiveDate::from_ymd_opt(self.year as i32, self.month as u32, self.day as u32)?
+            .and_hms_opt(self.hour as u32, self.minute as u32, self.second as u32)
+    }
+
+    /// Returns the timestamp at the start of `date`
+    pub fn start_of(date: chrono::NaiveDate) -> Self {
+        Self::from_datetime(date.and_time(chrono::NaiveTime::MIN))
+    }
+
+    /// Returns the timestamp of `datetime`, e.g. when a commit was written
+    pub fn from_datetime(datetime: chrono::NaiveDateTime) -> Self {
+        Self {
+            year: datetime.year() as u16,
+            month: datetime.month() as u8,
+            day: datetime.day() as u8,
+            hour: datetime.hour() as u8,
+            minute: datetime.minute() as u8,
+            second: datetime.second() as u8,
+        }
+    }
+
+    /// Formats the timestamp for showing to the user, using the format set with
+    /// [`set_display_format`]. The `Display` implementation is used as a
+    /// fallback and is also what gets stored in the database.
+    pub fn display(&self) -> String {
+        match (DISPLAY_FORMAT.get(), self.to_datetime()) {
+            (Some(format), Some(datetime)) => {
+                use std::fmt::Write;
+                let mut out = String::new();
+                match write!(out, "{}", datetime.format(format)) {
+                    Ok(()) => out,
+                    Err(_) => self.to_string(),
+                }
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl Default for TimeStamp {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
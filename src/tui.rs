@@ -0,0 +1,302 @@
+//! A full-screen interface for browsing entries, started with `code_diary tui`.
+//!
+//! The left pane lists the entries in the selected notebook, newest first, and
+//! the right pane shows the highlighted one the same way `view` does.
+
+use std::io;
+
+use ansi_to_tui::IntoText;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Text;
+use ratatui::widgets::{Block, Borders, List, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::backend::{get_entries, remove_id, store_entry, update_entry};
+use crate::compose_entry;
+use crate::config::Config;
+use crate::diaryentry::DiaryEntry;
+use crate::fail;
+use crate::frontend::prompt_for_content_in_editor;
+use crate::templates::EntryTemplate;
+
+/// How many lines `J`/`K` and page up/down scroll the detail pane
+const SCROLL_STEP: u16 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Browse,
+    Search,
+    ConfirmDelete,
+}
+
+struct App<'a> {
+    config: &'a Config,
+    entries: Vec<DiaryEntry>,
+    /// Indices into `entries` that match `query`
+    visible: Vec<usize>,
+    list_state: ListState,
+    scroll: u16,
+    query: String,
+    mode: Mode,
+    status: String,
+    quit: bool,
+}
+
+/// Runs the interface until the user quits
+/// # Panics
+/// Will 'gracefully' exit if the terminal cannot be used
+pub fn run(config: &Config) {
+    let mut app = App::new(config);
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    if let Err(e) = result {
+        fail!("Terminal Error: {e}");
+    }
+}
+
+impl<'a> App<'a> {
+    fn new(config: &'a Config) -> Self {
+        let mut app = Self {
+            config,
+            entries: Vec::new(),
+            visible: Vec::new(),
+            list_state: ListState::default(),
+            scroll: 0,
+            query: String::new(),
+            mode: Mode::Browse,
+            status: String::new(),
+            quit: false,
+        };
+        app.reload();
+        app
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_key(key, terminal)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn reload(&mut self) {
+        self.entries = get_entries();
+        self.entries.sort();
+        self.entries.reverse();
+        self.filter();
+    }
+
+    fn filter(&mut self) {
+        let query = self.query.to_lowercase();
+        self.visible = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| query.is_empty() || matches(entry, &query))
+            .map(|(index, _)| index)
+            .collect();
+
+        let selected = match self.list_state.selected() {
+            _ if self.visible.is_empty() => None,
+            Some(selected) => Some(selected.min(self.visible.len() - 1)),
+            None => Some(0),
+        };
+        self.list_state.select(selected);
+        self.scroll = 0;
+    }
+
+    fn selected(&self) -> Option<&DiaryEntry> {
+        let index = *self.visible.get(self.list_state.selected()?)?;
+        self.entries.get(index)
+    }
+
+    fn select(&mut self, index: usize) {
+        if !self.visible.is_empty() {
+            self.list_state
+                .select(Some(index.min(self.visible.len() - 1)));
+            self.scroll = 0;
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+
+        let mut title = format!(" Entries ({}) ", self.visible.len());
+        if !self.query.is_empty() {
+            title = format!(
+                " Entries ({}) matching '{}' ",
+                self.visible.len(),
+                self.query
+            );
+        }
+        let items: Vec<String> = self
+            .visible
+            .iter()
+            .map(|&index| self.entries[index].to_string())
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, left, &mut self.list_state);
+
+        let detail = match self.selected() {
+            Some(entry) => entry
+                .show_detail()
+                .into_text()
+                .unwrap_or_else(|_| Text::raw(entry.content.clone())),
+            None if self.entries.is_empty() => Text::raw("You have no entries, silly!"),
+            None => Text::raw("No entries match your search"),
+        };
+        let detail = Paragraph::new(detail)
+            .block(Block::default().borders(Borders::ALL).title(" Entry "))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        frame.render_widget(detail, right);
+
+        let line = match self.mode {
+            Mode::Search => format!("/{}", self.query),
+            Mode::ConfirmDelete => match self.selected() {
+                Some(entry) => format!("Delete '{entry}'? (y/n)"),
+                None => String::new(),
+            },
+            Mode::Browse if !self.status.is_empty() => self.status.clone(),
+            Mode::Browse => {
+                String::from("j/k move  J/K scroll  / search  a add  e edit  d delete  q quit")
+            }
+        };
+        frame.render_widget(Paragraph::new(line), status);
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        self.status.clear();
+        match self.mode {
+            Mode::Search => match key.code {
+                KeyCode::Enter => self.mode = Mode::Browse,
+                KeyCode::Esc => {
+                    self.query.clear();
+                    self.mode = Mode::Browse;
+                    self.filter();
+                }
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.filter();
+                }
+                KeyCode::Char(c) => {
+                    self.query.push(c);
+                    self.filter();
+                }
+                _ => (),
+            },
+            Mode::ConfirmDelete => {
+                if key.code == KeyCode::Char('y') {
+                    if let Some(entry) = self.selected() {
+                        crate::backup::automatic(self.config);
+                        remove_id(entry.id);
+                        self.status = format!("Deleted '{entry}'");
+                    }
+                    self.reload();
+                }
+                self.mode = Mode::Browse;
+            }
+            Mode::Browse => match key.code {
+                KeyCode::Char('q') => self.quit = true,
+                KeyCode::Esc if self.query.is_empty() => self.quit = true,
+                KeyCode::Esc => {
+                    self.query.clear();
+                    self.filter();
+                }
+                KeyCode::Char('j') | KeyCode::Down => {
+                    self.select(self.list_state.selected().map_or(0, |i| i + 1))
+                }
+                KeyCode::Char('k') | KeyCode::Up => self.select(
+                    self.list_state
+                        .selected()
+                        .map_or(0, |i| i.saturating_sub(1)),
+                ),
+                KeyCode::Char('g') | KeyCode::Home => self.select(0),
+                KeyCode::Char('G') | KeyCode::End => self.select(usize::MAX),
+                KeyCode::Char('J') | KeyCode::PageDown => {
+                    self.scroll = self.scroll.saturating_add(SCROLL_STEP)
+                }
+                KeyCode::Char('K') | KeyCode::PageUp => {
+                    self.scroll = self.scroll.saturating_sub(SCROLL_STEP)
+                }
+                KeyCode::Char('/') => self.mode = Mode::Search,
+                KeyCode::Char('d') if self.selected().is_some() => self.mode = Mode::ConfirmDelete,
+                KeyCode::Char('a') => {
+                    suspend(terminal, || self.add())?;
+                }
+                KeyCode::Char('e') => {
+                    suspend(terminal, || self.edit())?;
+                }
+                _ => (),
+            },
+        }
+        Ok(())
+    }
+
+    fn add(&mut self) {
+        // Asks for the same title, content and ratings as `add` does
+        let entry = compose_entry(
+            self.config,
+            None,
+            None,
+            Vec::new(),
+            Vec::new(),
+            EntryTemplate::default(),
+            false,
+        );
+        store_entry(&entry);
+
+        self.query.clear();
+        self.reload();
+        self.select(0);
+        self.status = format!("Added '{entry}'");
+    }
+
+    fn edit(&mut self) {
+        let mut entry = match self.selected() {
+            Some(entry) => entry.clone(),
+            None => return,
+        };
+        entry.content =
+            prompt_for_content_in_editor(&self.config.editor_or_default(), &entry.content);
+        update_entry(&entry);
+
+        self.reload();
+        self.status = format!("Saved '{entry}'");
+    }
+}
+
+/// Gives the terminal back to the shell while `action` runs, e.g. to open an
+/// editor or ask questions with `requestty`.
+fn suspend(terminal: &mut DefaultTerminal, action: impl FnOnce()) -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    action();
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    enable_raw_mode()?;
+    terminal.clear()
+}
+
+fn matches(entry: &DiaryEntry, query: &str) -> bool {
+    let title = entry.title.as_deref().unwrap_or("");
+    [title, &entry.content, &entry.date.display()]
+        .iter()
+        .any(|field| field.to_lowercase().contains(query))
+}
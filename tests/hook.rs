@@ -0,0 +1,52 @@
+//! Runs the built binary against a scratch repository and diary, for the
+//! behaviour that spans several commands.
+
+use std::path::Path;
+use std::process::Command;
+
+/// Runs `program` with `args` in `dir`, with a home directory of its own so
+/// neither git nor the diary see the user's configuration
+fn run(program: &str, args: &[&str], dir: &Path, home: &Path) {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env_remove("EDITOR")
+        .env_remove("VISUAL")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{program} {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn import_skips_the_commits_the_hook_recorded() {
+    let home = tempfile::tempdir().unwrap();
+    let repo = home.path().join("project");
+    std::fs::create_dir(&repo).unwrap();
+    let git = |args: &[&str]| run("git", args, &repo, home.path());
+    let diary = |args: &[&str]| run(env!("CARGO_BIN_EXE_code_diary"), args, &repo, home.path());
+
+    git(&["init", "--quiet"]);
+    git(&["config", "user.name", "Test"]);
+    git(&["config", "user.email", "test@example.com"]);
+    std::fs::write(repo.join("README.md"), "hello\n").unwrap();
+    git(&["add", "README.md"]);
+    git(&["commit", "--quiet", "-m", "Say hello"]);
+    diary(&["hook", "run", "--auto"]);
+    diary(&["import", "--git", "."]);
+
+    let db = sqlite::open(home.path().join("code_diary.db")).unwrap();
+    let mut titles = Vec::new();
+    db.iterate("SELECT Title FROM Entries;", |pairs| {
+        titles.push(pairs[0].1.unwrap_or_default().to_string());
+        true
+    })
+    .unwrap();
+    assert_eq!(titles, vec!["Say hello"]);
+}